@@ -1,17 +1,21 @@
+#[allow(deprecated)]
+use solana_program::program_error::PrintProgramError;
 use solana_program::{
     account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, pubkey::Pubkey,
 };
 
-use crate::processor::Processor;
+use crate::{error::VaultError, processor::Processor};
 
 entrypoint!(process_instruction);
 
+#[allow(deprecated)]
 pub fn process_instruction(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
     if let Err(error) = Processor::process(program_id, accounts, instruction_data) {
+        error.print::<VaultError>();
         return Err(error);
     }
 
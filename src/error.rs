@@ -0,0 +1,155 @@
+#![allow(deprecated)]
+
+use num_derive::FromPrimitive;
+use solana_program::{
+    decode_error::DecodeError, msg, program_error::PrintProgramError, program_error::ProgramError,
+};
+use thiserror::Error;
+
+/// Errors returned by the vault program.
+///
+/// The discriminants are part of the program's public interface and must not
+/// be reordered; add new variants at the end.
+#[derive(Clone, Copy, Debug, Eq, Error, FromPrimitive, PartialEq)]
+pub enum VaultError {
+    #[error("Invalid instruction data")]
+    InvalidInstruction = 0,
+    #[error("Required signature is missing")]
+    MissingRequiredSignature = 1,
+    #[error("Vault state account does not match the expected PDA")]
+    InvalidStatePda = 2,
+    #[error("Vault state account is not owned by the vault program")]
+    InvalidStateOwner = 3,
+    #[error("Vault state account data could not be decoded")]
+    InvalidStateData = 4,
+    #[error("Vault state account is already initialized")]
+    AlreadyInitialized = 5,
+    #[error("Signer is not the vault authority")]
+    Unauthorized = 6,
+    #[error("Vault token account does not match the vault state")]
+    InvalidVaultAccount = 7,
+    #[error("Incorrect token program")]
+    InvalidTokenProgram = 8,
+    #[error("Incorrect system program")]
+    InvalidSystemProgram = 9,
+    #[error("Incorrect associated token program")]
+    InvalidAssociatedTokenProgram = 10,
+    #[error("Token account is not owned by the token program")]
+    InvalidTokenAccount = 11,
+    #[error("Token account has an unexpected owner")]
+    InvalidTokenAccountOwner = 12,
+    #[error("Token accounts have mismatched mints")]
+    MintMismatch = 13,
+    #[error("Vault token account still holds tokens")]
+    VaultNotEmpty = 14,
+    #[error("Account is not tagged with the expected account type")]
+    InvalidAccountType = 15,
+    #[error("Vault state layout version is not supported")]
+    UnsupportedStateVersion = 16,
+    #[error("Vault state account already uses the current layout")]
+    AlreadyMigrated = 17,
+    #[error("Vault has no pending authority to accept")]
+    NoPendingAuthority = 18,
+    #[error("Multisig signer set or threshold is invalid")]
+    InvalidMultisigConfig = 19,
+    #[error("Not enough multisig signers approved the instruction")]
+    NotEnoughSigners = 20,
+    #[error("Vault is not configured with a multisig signer set")]
+    NotMultisigVault = 21,
+    #[error("Signer is not a member of the vault's signer set")]
+    NotASigner = 22,
+    #[error("Withdrawal proposal account is invalid for this vault")]
+    InvalidProposal = 23,
+    #[error("Withdrawal proposal is no longer pending")]
+    ProposalNotPending = 24,
+    #[error("Destination does not match the withdrawal proposal")]
+    InvalidDestination = 25,
+    #[error("Arithmetic overflow")]
+    ArithmeticOverflow = 26,
+    #[error("Withdraw delay must not be negative")]
+    InvalidWithdrawDelay = 27,
+    #[error("Vault is timelocked; withdrawals must be requested first")]
+    WithdrawTimelocked = 28,
+    #[error("Pending withdrawal account is invalid for this vault")]
+    InvalidWithdrawalRequest = 29,
+    #[error("Withdrawal request is no longer pending")]
+    WithdrawalNotPending = 30,
+    #[error("Withdrawal delay has not elapsed yet")]
+    TimelockNotElapsed = 31,
+    #[error("Rate limit configuration is invalid")]
+    InvalidRateLimit = 32,
+    #[error("Withdrawal exceeds the vault's rate limit")]
+    RateLimitExceeded = 33,
+    #[error("Vault is paused")]
+    VaultPaused = 34,
+    #[error("Destination allowlist account is invalid for this vault")]
+    InvalidAllowlist = 35,
+    #[error("Destination is not on the vault's allowlist")]
+    DestinationNotAllowed = 36,
+    #[error("Destination is already on the allowlist")]
+    AllowlistEntryExists = 37,
+    #[error("Destination is not on the allowlist")]
+    AllowlistEntryNotFound = 38,
+    #[error("Delegate account is invalid for this vault")]
+    InvalidDelegate = 39,
+    #[error("Delegation has expired")]
+    DelegateExpired = 40,
+    #[error("Withdrawal exceeds the delegate's allowance")]
+    AllowanceExceeded = 41,
+    #[error("Share mint does not match the vault state")]
+    InvalidShareMint = 42,
+    #[error("Vault is not a pooled vault")]
+    NotPooledVault = 43,
+    #[error("Amount is too small to convert between shares and underlying")]
+    ZeroShares = 44,
+    #[error("Pool has outstanding shares but no assets")]
+    PoolInsolvent = 45,
+    #[error("Vault is not refundable")]
+    NotRefundableVault = 46,
+    #[error("Depositor receipt account is invalid for this vault")]
+    InvalidReceipt = 47,
+    #[error("Amount exceeds the depositor's recorded balance")]
+    InsufficientReceiptBalance = 48,
+    #[error("Vault configuration options conflict")]
+    InvalidVaultConfig = 49,
+    #[error("Mint has an extension the vault cannot safely hold")]
+    UnsupportedMintExtension = 50,
+    #[error("Asset slot account is invalid for this vault")]
+    InvalidAsset = 51,
+    #[error("Vault already holds this mint")]
+    AssetExists = 52,
+    #[error("Vault still has registered asset slots")]
+    VaultHasAssets = 53,
+    #[error("Unwrap account is invalid for this vault")]
+    InvalidUnwrapAccount = 54,
+    #[error("Amount exceeds the vault's withdrawable balance")]
+    InsufficientVaultBalance = 55,
+    #[error("Vault has no withdraw delay; withdraw directly")]
+    NotTimelocked = 56,
+    #[error("Pooled vault assets only leave through Redeem")]
+    PooledVault = 57,
+    #[error("Refundable vaults take deposits through DepositWithReceipt")]
+    ReceiptRequired = 58,
+    #[error("Asset withdrawals are disabled while a rate limit is set")]
+    AssetRateLimited = 59,
+    #[error("Vault still owes share or receipt holders")]
+    OutstandingClaims = 60,
+}
+
+impl From<VaultError> for ProgramError {
+    fn from(e: VaultError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl<T> DecodeError<T> for VaultError {
+    fn type_of() -> &'static str {
+        "VaultError"
+    }
+}
+
+impl PrintProgramError for VaultError {
+    fn print<E>(&self) {
+        msg!("Error: {}", self);
+    }
+}
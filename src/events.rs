@@ -0,0 +1,319 @@
+//! Structured events emitted with `sol_log_data`.
+//!
+//! Each event is logged as a single data field: `EVENT_PREFIX` followed by
+//! the borsh-serialized `VaultEvent`, whose variant index identifies the
+//! event. Validators print it as `Program data: <base64>`; `decode_events`
+//! turns those log lines back into events, keeping only data logged while
+//! this program was the innermost invoked program.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, log::sol_log_data, pubkey::Pubkey};
+
+use crate::state::{vault_state::RateLimit, withdrawal_proposal::ProposalStatus};
+
+/// Marks `sol_log_data` payloads written by this program.
+pub const EVENT_PREFIX: [u8; 8] = *b"vaultevt";
+
+const PROGRAM_DATA_LOG: &str = "Program data: ";
+const PROGRAM_LOG: &str = "Program";
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct VaultInitialized {
+    pub vault_state: Pubkey,
+    pub authority: Pubkey,
+    pub token_mint: Pubkey,
+    pub is_native: bool,
+    pub vault_id: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Deposited {
+    pub vault_state: Pubkey,
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub shares: u64, // shares minted, 0 for non-pooled vaults
+}
+
+/// Emitted for every transfer out of a vault, whichever instruction caused it.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Withdrawn {
+    pub vault_state: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Closed {
+    pub vault_state: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct StateMigrated {
+    pub vault_state: Pubkey,
+    pub version: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct AuthorityProposed {
+    pub vault_state: Pubkey,
+    pub pending_authority: Pubkey, // default when a proposal is cancelled
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct AuthorityChanged {
+    pub vault_state: Pubkey,
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct ProposalCreated {
+    pub vault_state: Pubkey,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+}
+
+/// Emitted by `ApproveProposal` and `RejectProposal`.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct ProposalVoted {
+    pub vault_state: Pubkey,
+    pub proposal_id: u64,
+    pub voter: Pubkey,
+    pub approve: bool,
+    pub status: ProposalStatus, // status after the vote
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct ProposalExecuted {
+    pub vault_state: Pubkey,
+    pub proposal_id: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct WithdrawalRequested {
+    pub vault_state: Pubkey,
+    pub request_id: u64,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub requested_at: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct WithdrawalExecuted {
+    pub vault_state: Pubkey,
+    pub request_id: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct WithdrawalCancelled {
+    pub vault_state: Pubkey,
+    pub request_id: u64,
+    pub cancelled_by: Pubkey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct RateLimitUpdated {
+    pub vault_state: Pubkey,
+    pub rate_limit: RateLimit,
+    pub effective_at: i64, // when the limit applies; now when set immediately
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Paused {
+    pub vault_state: Pubkey,
+    pub paused_by: Pubkey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Unpaused {
+    pub vault_state: Pubkey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct AllowedDestinationAdded {
+    pub vault_state: Pubkey,
+    pub destination: Pubkey,
+    pub allowed_at: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct AllowedDestinationRemoved {
+    pub vault_state: Pubkey,
+    pub destination: Pubkey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct DelegateApproved {
+    pub vault_state: Pubkey,
+    pub delegate: Pubkey,
+    pub allowance: u64,
+    pub expires_at: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct DelegateRevoked {
+    pub vault_state: Pubkey,
+    pub delegate: Pubkey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct DelegatedWithdrawal {
+    pub vault_state: Pubkey,
+    pub delegate: Pubkey,
+    pub amount: u64,
+    pub remaining_allowance: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Redeemed {
+    pub vault_state: Pubkey,
+    pub owner: Pubkey,
+    pub shares: u64,
+    pub amount: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Refunded {
+    pub vault_state: Pubkey,
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub remaining_balance: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct AssetAdded {
+    pub vault_state: Pubkey,
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct AssetRemoved {
+    pub vault_state: Pubkey,
+    pub mint: Pubkey,
+}
+
+/// Emitted instead of `Deposited` for deposits into an asset slot.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct AssetDeposited {
+    pub vault_state: Pubkey,
+    pub mint: Pubkey,
+    pub depositor: Pubkey,
+    pub amount: u64,
+}
+
+/// Emitted instead of `Withdrawn` for transfers out of an asset slot.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct AssetWithdrawn {
+    pub vault_state: Pubkey,
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+/// Every event the program emits.
+///
+/// Variants are serialized by index, so new ones must only be appended.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum VaultEvent {
+    VaultInitialized(VaultInitialized),
+    Deposited(Deposited),
+    Withdrawn(Withdrawn),
+    Closed(Closed),
+    StateMigrated(StateMigrated),
+    AuthorityProposed(AuthorityProposed),
+    AuthorityChanged(AuthorityChanged),
+    ProposalCreated(ProposalCreated),
+    ProposalVoted(ProposalVoted),
+    ProposalExecuted(ProposalExecuted),
+    WithdrawalRequested(WithdrawalRequested),
+    WithdrawalExecuted(WithdrawalExecuted),
+    WithdrawalCancelled(WithdrawalCancelled),
+    RateLimitUpdated(RateLimitUpdated),
+    Paused(Paused),
+    Unpaused(Unpaused),
+    AllowedDestinationAdded(AllowedDestinationAdded),
+    AllowedDestinationRemoved(AllowedDestinationRemoved),
+    DelegateApproved(DelegateApproved),
+    DelegateRevoked(DelegateRevoked),
+    DelegatedWithdrawal(DelegatedWithdrawal),
+    Redeemed(Redeemed),
+    Refunded(Refunded),
+    AssetAdded(AssetAdded),
+    AssetRemoved(AssetRemoved),
+    AssetDeposited(AssetDeposited),
+    AssetWithdrawn(AssetWithdrawn),
+}
+
+impl VaultEvent {
+    pub fn emit(&self) -> ProgramResult {
+        let mut data = EVENT_PREFIX.to_vec();
+        self.serialize(&mut data)?;
+        sol_log_data(&[&data]);
+        Ok(())
+    }
+
+    /// Decodes one `sol_log_data` field, returning `None` for data that was
+    /// not written by `emit`.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let payload = data.strip_prefix(&EVENT_PREFIX)?;
+        Self::try_from_slice(payload).ok()
+    }
+}
+
+/// Collects the vault events `program_id` logged in a transaction's log
+/// messages, in order. The `Program <id> invoke` / `success` / `failed` lines
+/// track which program is running, and data logged by any other program,
+/// including programs the vault calls, is skipped.
+pub fn decode_events<S: AsRef<str>>(program_id: &Pubkey, logs: &[S]) -> Vec<VaultEvent> {
+    let program_id = program_id.to_string();
+    let mut frames: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for log in logs {
+        let log = log.as_ref();
+
+        if let Some(fields) = log.strip_prefix(PROGRAM_DATA_LOG) {
+            if frames.last() == Some(&program_id.as_str()) {
+                events.extend(
+                    fields
+                        .split(' ')
+                        .filter_map(|field| STANDARD.decode(field).ok())
+                        .filter_map(|data| VaultEvent::decode(&data)),
+                );
+            }
+
+            continue;
+        }
+
+        let mut words = log.split(' ');
+
+        if words.next() != Some(PROGRAM_LOG) {
+            continue;
+        }
+
+        // only the runtime writes a program id after `Program`; anything a
+        // program logs itself starts with `Program log:` or `Program data:`
+        let (Some(id), Some(status)) = (words.next(), words.next()) else {
+            continue;
+        };
+
+        if id.parse::<Pubkey>().is_err() {
+            continue;
+        }
+
+        match status {
+            "invoke" => frames.push(id),
+            "success" | "failed:" => {
+                frames.pop();
+            }
+            _ => {}
+        }
+    }
+
+    events
+}
@@ -0,0 +1,52 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::VaultError,
+    events::{AuthorityChanged, VaultEvent},
+    state::vault_state::VaultState,
+};
+
+pub fn accept_authority(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let new_authority = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+
+    if !new_authority.is_signer {
+        return Err(VaultError::MissingRequiredSignature.into());
+    }
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    if !vault_state.has_pending_authority() {
+        return Err(VaultError::NoPendingAuthority.into());
+    }
+
+    if &vault_state.pending_authority != new_authority.key {
+        return Err(VaultError::Unauthorized.into());
+    }
+
+    let previous_authority = vault_state.authority;
+    vault_state.authority = vault_state.pending_authority;
+    vault_state.pending_authority = Pubkey::default();
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    VaultEvent::AuthorityChanged(AuthorityChanged {
+        vault_state: *vault_state_pda.key,
+        previous_authority,
+        new_authority: *new_authority.key,
+    })
+    .emit()?;
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program::invoke,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::transfer,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::VaultError,
+    events::{AllowedDestinationAdded, VaultEvent},
+    instructions::initialize::create_pda_account,
+    state::{
+        account_type::AccountType,
+        destination_allowlist::{
+            AllowedDestination, DestinationAllowlist, ALLOWLIST_ADDITION_DELAY,
+        },
+        vault_state::VaultState,
+    },
+};
+
+pub fn add_allowed_destination(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    destination: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let authority = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let allowlist_account = next_account_info(accounts_iter)?;
+    let payer = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if !payer.is_signer {
+        return Err(VaultError::MissingRequiredSignature.into());
+    }
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    vault_state.check_authority(authority, accounts_iter.as_slice())?;
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(VaultError::InvalidSystemProgram.into());
+    }
+
+    let mut allowlist = if allowlist_account.data_is_empty() {
+        let (expected_allowlist_pda, bump) = Pubkey::find_program_address(
+            &[
+                b"allowlist",
+                vault_state_pda.key.as_ref(),
+                &vault_state.generation.to_le_bytes(),
+            ],
+            program_id,
+        );
+
+        if allowlist_account.key != &expected_allowlist_pda {
+            return Err(VaultError::InvalidAllowlist.into());
+        }
+
+        create_pda_account(
+            payer,
+            allowlist_account,
+            DestinationAllowlist::BASE_LEN,
+            program_id,
+            system_program,
+            &[
+                b"allowlist",
+                vault_state_pda.key.as_ref(),
+                &vault_state.generation.to_le_bytes(),
+                &[bump],
+            ],
+        )?;
+
+        // creating the allowlist turns it on for good; an empty list blocks
+        // every withdrawal until an entry matures
+        vault_state.allowlist_enabled = true;
+        vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+        DestinationAllowlist {
+            account_type: AccountType::DestinationAllowlist,
+            vault_state: *vault_state_pda.key,
+            bump,
+            entries: Vec::new(),
+        }
+    } else {
+        if allowlist_account.owner != program_id {
+            return Err(VaultError::InvalidAllowlist.into());
+        }
+
+        let allowlist = DestinationAllowlist::unpack(&allowlist_account.data.borrow())?;
+
+        allowlist.verify_pda(
+            allowlist_account.key,
+            vault_state_pda.key,
+            vault_state.generation,
+            program_id,
+        )?;
+
+        allowlist
+    };
+
+    if allowlist.position(&destination).is_some() {
+        return Err(VaultError::AllowlistEntryExists.into());
+    }
+
+    let allowed_at = Clock::get()?
+        .unix_timestamp
+        .checked_add(ALLOWLIST_ADDITION_DELAY)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+
+    allowlist.entries.push(AllowedDestination {
+        destination,
+        allowed_at,
+    });
+
+    let new_len = allowlist.space();
+    let shortfall = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(allowlist_account.lamports());
+
+    if shortfall > 0 {
+        invoke(
+            &transfer(payer.key, allowlist_account.key, shortfall),
+            &[
+                payer.clone(),
+                allowlist_account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+
+    allowlist_account.resize(new_len)?;
+    allowlist.pack(&mut allowlist_account.data.borrow_mut())?;
+
+    VaultEvent::AllowedDestinationAdded(AllowedDestinationAdded {
+        vault_state: *vault_state_pda.key,
+        destination,
+        allowed_at,
+    })
+    .emit()?;
+
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use std::slice::Iter;
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use spl_token_2022::check_spl_token_program_account;
+
+use crate::{
+    error::VaultError,
+    events::{AssetAdded, VaultEvent},
+    instructions::initialize::{create_pda_account, mint_decimals},
+    state::{account_type::AccountType, vault_asset::VaultAsset, vault_state::VaultState},
+};
+
+pub fn add_asset(program_id: &Pubkey, accounts: &[AccountInfo], mint: Pubkey) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let authority = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let asset_account = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let token_account = next_account_info(accounts_iter)?;
+    let payer = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let associated_token_program = next_account_info(accounts_iter)?;
+
+    if !payer.is_signer {
+        return Err(VaultError::MissingRequiredSignature.into());
+    }
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    vault_state.check_authority(authority, accounts_iter.as_slice())?;
+
+    if token_mint.key != &mint {
+        return Err(VaultError::MintMismatch.into());
+    }
+
+    if !vault_state.is_native && mint == vault_state.token_mint {
+        return Err(VaultError::AssetExists.into());
+    }
+
+    if !asset_account.data_is_empty() {
+        return Err(VaultError::AssetExists.into());
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(VaultError::InvalidSystemProgram.into());
+    }
+
+    if associated_token_program.key != &spl_associated_token_account::id() {
+        return Err(VaultError::InvalidAssociatedTokenProgram.into());
+    }
+
+    check_spl_token_program_account(token_program.key)
+        .map_err(|_| VaultError::InvalidTokenProgram)?;
+
+    // assets never get the `allow_unsafe_extensions` escape hatch
+    let decimals = mint_decimals(token_mint, token_program, false)?;
+
+    let (expected_asset_pda, bump) = Pubkey::find_program_address(
+        &[b"asset", vault_state_pda.key.as_ref(), mint.as_ref()],
+        program_id,
+    );
+
+    if asset_account.key != &expected_asset_pda {
+        return Err(VaultError::InvalidAsset.into());
+    }
+
+    let expected_ata =
+        get_associated_token_address_with_program_id(vault_state_pda.key, &mint, token_program.key);
+
+    if token_account.key != &expected_ata {
+        return Err(VaultError::InvalidVaultAccount.into());
+    }
+
+    create_pda_account(
+        payer,
+        asset_account,
+        VaultAsset::LEN,
+        program_id,
+        system_program,
+        &[
+            b"asset",
+            vault_state_pda.key.as_ref(),
+            mint.as_ref(),
+            &[bump],
+        ],
+    )?;
+
+    // idempotent, as anyone can create the state PDA's ATA beforehand
+    invoke(
+        &create_associated_token_account_idempotent(
+            payer.key,
+            vault_state_pda.key,
+            &mint,
+            token_program.key,
+        ),
+        &[
+            payer.clone(),
+            token_account.clone(),
+            vault_state_pda.clone(),
+            token_mint.clone(),
+            system_program.clone(),
+            token_program.clone(),
+            associated_token_program.clone(),
+        ],
+    )?;
+
+    VaultAsset {
+        account_type: AccountType::VaultAsset,
+        vault_state: *vault_state_pda.key,
+        mint,
+        token_account: expected_ata,
+        token_program: *token_program.key,
+        decimals,
+        total_deposited: 0,
+        total_withdrawn: 0,
+        bump,
+    }
+    .pack(&mut asset_account.data.borrow_mut())?;
+
+    vault_state.asset_count = vault_state
+        .asset_count
+        .checked_add(1)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    VaultEvent::AssetAdded(AssetAdded {
+        vault_state: *vault_state_pda.key,
+        mint,
+        token_account: expected_ata,
+    })
+    .emit()?;
+
+    Ok(())
+}
+
+/// Takes the asset's mint and slot from `accounts_iter` and checks the slot
+/// belongs to the vault and holds that mint.
+pub fn next_asset<'a, 'info>(
+    program_id: &Pubkey,
+    vault_state: &Pubkey,
+    accounts_iter: &mut Iter<'a, AccountInfo<'info>>,
+) -> Result<(&'a AccountInfo<'info>, &'a AccountInfo<'info>, VaultAsset), ProgramError> {
+    let token_mint = next_account_info(accounts_iter)?;
+    let asset_account = next_account_info(accounts_iter)?;
+
+    if asset_account.owner != program_id {
+        return Err(VaultError::InvalidAsset.into());
+    }
+
+    let asset = VaultAsset::unpack(&asset_account.data.borrow())?;
+
+    asset.verify_pda(asset_account.key, vault_state, program_id)?;
+
+    if token_mint.key != &asset.mint {
+        return Err(VaultError::MintMismatch.into());
+    }
+
+    Ok((token_mint, asset_account, asset))
+}
@@ -0,0 +1,120 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::VaultError,
+    events::{DelegateApproved, VaultEvent},
+    instructions::initialize::create_pda_account,
+    state::{account_type::AccountType, delegate::Delegate, vault_state::VaultState},
+};
+
+pub fn approve_delegate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    delegate: Pubkey,
+    allowance: u64,
+    expires_at: i64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let authority = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let delegate_account = next_account_info(accounts_iter)?;
+    let payer = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if !payer.is_signer {
+        return Err(VaultError::MissingRequiredSignature.into());
+    }
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    vault_state.check_authority(authority, accounts_iter.as_slice())?;
+
+    let mut delegation = if delegate_account.data_is_empty() {
+        if system_program.key != &solana_program::system_program::id() {
+            return Err(VaultError::InvalidSystemProgram.into());
+        }
+
+        let (expected_delegate_pda, bump) = Pubkey::find_program_address(
+            &[
+                b"delegate",
+                vault_state_pda.key.as_ref(),
+                &vault_state.generation.to_le_bytes(),
+                delegate.as_ref(),
+            ],
+            program_id,
+        );
+
+        if delegate_account.key != &expected_delegate_pda {
+            return Err(VaultError::InvalidDelegate.into());
+        }
+
+        create_pda_account(
+            payer,
+            delegate_account,
+            Delegate::LEN,
+            program_id,
+            system_program,
+            &[
+                b"delegate",
+                vault_state_pda.key.as_ref(),
+                &vault_state.generation.to_le_bytes(),
+                delegate.as_ref(),
+                &[bump],
+            ],
+        )?;
+
+        Delegate {
+            account_type: AccountType::Delegate,
+            vault_state: *vault_state_pda.key,
+            delegate,
+            allowance: 0,
+            expires_at: 0,
+            bump,
+        }
+    } else {
+        if delegate_account.owner != program_id {
+            return Err(VaultError::InvalidDelegate.into());
+        }
+
+        let delegation = Delegate::unpack(&delegate_account.data.borrow())?;
+
+        delegation.verify_pda(
+            delegate_account.key,
+            vault_state_pda.key,
+            vault_state.generation,
+            program_id,
+        )?;
+
+        if delegation.delegate != delegate {
+            return Err(VaultError::InvalidDelegate.into());
+        }
+
+        delegation
+    };
+
+    // approving again replaces the previous allowance and expiry
+    delegation.allowance = allowance;
+    delegation.expires_at = expires_at;
+    delegation.pack(&mut delegate_account.data.borrow_mut())?;
+
+    VaultEvent::DelegateApproved(DelegateApproved {
+        vault_state: *vault_state_pda.key,
+        delegate,
+        allowance,
+        expires_at,
+    })
+    .emit()?;
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::VaultError,
+    events::{ProposalVoted, VaultEvent},
+    state::{
+        vault_state::VaultState,
+        withdrawal_proposal::{ProposalStatus, WithdrawalProposal},
+    },
+};
+
+pub fn approve_proposal(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let signer = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let proposal_account = next_account_info(accounts_iter)?;
+
+    if !signer.is_signer {
+        return Err(VaultError::MissingRequiredSignature.into());
+    }
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    let signer_index = vault_state
+        .signer_index(signer.key)
+        .ok_or(VaultError::NotASigner)?;
+
+    if proposal_account.owner != program_id {
+        return Err(VaultError::InvalidProposal.into());
+    }
+
+    let mut proposal = WithdrawalProposal::unpack(&proposal_account.data.borrow())?;
+
+    proposal.verify_pda(
+        proposal_account.key,
+        vault_state_pda.key,
+        vault_state.generation,
+        program_id,
+    )?;
+
+    if proposal.status != ProposalStatus::Pending {
+        return Err(VaultError::ProposalNotPending.into());
+    }
+
+    proposal.record_vote(signer_index, true);
+    proposal.pack(&mut proposal_account.data.borrow_mut())?;
+
+    VaultEvent::ProposalVoted(ProposalVoted {
+        vault_state: *vault_state_pda.key,
+        proposal_id: proposal.proposal_id,
+        voter: *signer.key,
+        approve: true,
+        status: proposal.status,
+    })
+    .emit()?;
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::VaultError,
+    events::{VaultEvent, WithdrawalCancelled},
+    state::{
+        pending_withdrawal::{PendingWithdrawal, WithdrawalStatus},
+        vault_state::VaultState,
+    },
+};
+
+pub fn cancel_withdraw(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let canceller = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let pending_withdrawal_account = next_account_info(accounts_iter)?;
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    // the guardian can cancel on its own; anyone else goes through the
+    // vault's normal authority check
+    if !(canceller.is_signer && vault_state.is_guardian(canceller.key)) {
+        vault_state.check_authority(canceller, accounts_iter.as_slice())?;
+    }
+
+    if pending_withdrawal_account.owner != program_id {
+        return Err(VaultError::InvalidWithdrawalRequest.into());
+    }
+
+    let mut pending_withdrawal =
+        PendingWithdrawal::unpack(&pending_withdrawal_account.data.borrow())?;
+
+    pending_withdrawal.verify_pda(
+        pending_withdrawal_account.key,
+        vault_state_pda.key,
+        vault_state.generation,
+        program_id,
+    )?;
+
+    if pending_withdrawal.status != WithdrawalStatus::Pending {
+        return Err(VaultError::WithdrawalNotPending.into());
+    }
+
+    pending_withdrawal.status = WithdrawalStatus::Cancelled;
+    pending_withdrawal.pack(&mut pending_withdrawal_account.data.borrow_mut())?;
+
+    VaultEvent::WithdrawalCancelled(WithdrawalCancelled {
+        vault_state: *vault_state_pda.key,
+        request_id: pending_withdrawal.request_id,
+        cancelled_by: *canceller.key,
+    })
+    .emit()?;
+
+    Ok(())
+}
@@ -1,18 +1,24 @@
-use borsh::BorshDeserialize;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    clock::Clock,
     entrypoint::ProgramResult,
-    msg,
     program::invoke_signed,
-    program_error::ProgramError,
-    program_pack::Pack,
     pubkey::Pubkey,
+    rent::Rent,
     system_instruction::transfer,
+    sysvar::Sysvar,
+};
+use spl_token_2022::{extension::StateWithExtensions, instruction::close_account, state::Mint};
+
+use crate::{
+    error::VaultError,
+    events::{Closed, VaultEvent},
+    instructions::{
+        redeem::vault_assets,
+        withdraw::{check_allowed_destination, transfer_from_vault, VaultTransferAccounts},
+    },
+    state::{account_type::AccountType, closed_vault::ClosedVault, vault_state::VaultState},
 };
-use spl_token::instruction::close_account;
-use spl_token::state::Account as TokenAccount;
-
-use crate::state::vault_state::VaultState;
 
 pub fn close(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_iter = &mut accounts.iter();
@@ -24,31 +30,90 @@ pub fn close(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let token_program = next_account_info(account_iter)?;
     let system_program = next_account_info(account_iter)?;
 
-    if !authority.is_signer {
-        return Err(ProgramError::MissingRequiredSignature);
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    let token_mint = if vault_state.is_native {
+        None
+    } else {
+        Some(next_account_info(account_iter)?)
+    };
+
+    let share_mint = if vault_state.is_pooled() {
+        Some(next_account_info(account_iter)?)
+    } else {
+        None
+    };
+
+    let clock = Clock::get()?;
+
+    if vault_state.allowlist_enabled {
+        check_allowed_destination(
+            program_id,
+            vault_state_pda.key,
+            vault_state.generation,
+            next_account_info(account_iter)?,
+            authority_token_account.key,
+            clock.unix_timestamp,
+        )?;
+    }
+
+    vault_state.check_authority(authority, account_iter.as_slice())?;
+
+    vault_state.check_not_paused()?;
+
+    // the vault's balance belongs to share holders and receipt holders until
+    // they have redeemed or been refunded
+    if let Some(share_mint) = share_mint {
+        if share_mint.key != &vault_state.share_mint {
+            return Err(VaultError::InvalidShareMint.into());
+        }
+
+        let supply = StateWithExtensions::<Mint>::unpack(&share_mint.data.borrow())
+            .map_err(|_| VaultError::InvalidShareMint)?
+            .base
+            .supply;
+
+        if supply > 0 {
+            return Err(VaultError::OutstandingClaims.into());
+        }
     }
 
-    let vault_state = VaultState::try_from_slice(&vault_state_pda.data.borrow())?;
+    if vault_state.outstanding_receipts > 0 {
+        return Err(VaultError::OutstandingClaims.into());
+    }
 
-    if &vault_state.authority != authority.key {
-        return Err(ProgramError::IllegalOwner);
+    // asset slots hold the state PDA's other token accounts, which would be
+    // stranded once the state is gone
+    if vault_state.asset_count > 0 {
+        return Err(VaultError::VaultHasAssets.into());
     }
 
     if vault_token_account.key != &vault_state.token_account {
-        return Err(ProgramError::InvalidAccountData);
+        return Err(VaultError::InvalidVaultAccount.into());
     }
 
-    if vault_state_pda.owner != program_id {
-        return Err(ProgramError::InvalidAccountOwner);
+    // the sweep is an outflow like any other
+    let remaining = vault_assets(&vault_state, vault_token_account)?;
+
+    if remaining > 0 && vault_state.is_timelocked() {
+        return Err(VaultError::WithdrawTimelocked.into());
     }
 
+    vault_state.consume_rate_limit(remaining, &clock)?;
+
     if vault_state.is_native {
         if system_program.key != &solana_program::system_program::id() {
-            return Err(ProgramError::IncorrectProgramId);
+            return Err(VaultError::InvalidSystemProgram.into());
         }
 
         if vault_token_account.owner != system_program.key {
-            return Err(ProgramError::InvalidAccountOwner);
+            return Err(VaultError::InvalidVaultAccount.into());
         }
 
         invoke_signed(
@@ -68,32 +133,30 @@ pub fn close(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
                 &[vault_state.vault_bump],
             ]],
         )?;
-
-        invoke_signed(
-            &transfer(
-                vault_state_pda.key,
-                authority.key,
-                vault_state_pda.lamports(),
-            ),
-            &[
-                vault_state_pda.clone(),
-                authority.clone(),
-                system_program.clone(),
-            ],
-            &[&[b"state", authority.key.as_ref(), &[vault_state.state_bump]]],
-        )?
     } else {
-        let vault_token = TokenAccount::unpack(&vault_token_account.data.borrow())?;
-        if vault_token.amount != 0 {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        vault_state.check_token_program(token_program.key)?;
 
-        if token_program.key != &spl_token::id() {
-            return Err(ProgramError::IncorrectProgramId);
+        if system_program.key != &solana_program::system_program::id() {
+            return Err(VaultError::InvalidSystemProgram.into());
         }
 
-        if system_program.key != &solana_program::system_program::id() {
-            return Err(ProgramError::IncorrectProgramId);
+        // 1. Sweep whatever is left to the authority's token account, which
+        // must be the authority's and hold the same mint
+        if remaining > 0 {
+            transfer_from_vault(
+                &vault_state,
+                &VaultTransferAccounts {
+                    vault_state_pda,
+                    source_token_account: vault_token_account,
+                    destination_token_account: authority_token_account,
+                    token_mint,
+                    token_program,
+                    system_program,
+                    transfer_hook_accounts: accounts,
+                },
+                remaining,
+                Some(authority.key),
+            )?;
         }
 
         // 2. Close token account
@@ -105,38 +168,66 @@ pub fn close(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
             &[],
         )?;
 
-        invoke_signed(
-            &close_ix,
-            &[
-                vault_token_account.clone(),
-                authority.clone(),
-                vault_state_pda.clone(),
-                token_program.clone(),
-            ],
-            &[&[
-                b"vault",
-                vault_state_pda.key.as_ref(),
-                &[vault_state.vault_bump],
-            ]],
-        )?;
+        vault_state.with_signer_seeds(|seeds| {
+            invoke_signed(
+                &close_ix,
+                &[
+                    vault_token_account.clone(),
+                    authority.clone(),
+                    vault_state_pda.clone(),
+                    token_program.clone(),
+                ],
+                &[seeds],
+            )
+        })?;
+    }
 
-        // 3. Close state PDA
-        invoke_signed(
-            &transfer(
-                vault_state_pda.key,
-                authority.key,
-                vault_state_pda.lamports(),
-            ),
-            &[
-                vault_state_pda.clone(),
-                authority.clone(),
-                system_program.clone(),
-            ],
-            &[&[b"state", authority.key.as_ref(), &[vault_state.state_bump]]],
-        )?;
+    // 3. Leave a tombstone at the state PDA, so a vault reopened there starts
+    // a new generation, and refund the rest of its rent
+    let tombstone_rent = Rent::get()?.minimum_balance(ClosedVault::LEN);
+    let refund = vault_state_pda
+        .lamports()
+        .checked_sub(tombstone_rent)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    **authority.try_borrow_mut_lamports()? = authority
+        .lamports()
+        .checked_add(refund)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    **vault_state_pda.try_borrow_mut_lamports()? = tombstone_rent;
+
+    vault_state_pda.data.borrow_mut().fill(0);
+    vault_state_pda.resize(ClosedVault::LEN)?;
+    ClosedVault {
+        account_type: AccountType::ClosedVault,
+        generation: vault_state.generation,
     }
+    .pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    VaultEvent::Closed(Closed {
+        vault_state: *vault_state_pda.key,
+        authority: *authority.key,
+    })
+    .emit()?;
+
+    Ok(())
+}
 
-    msg!("vault closed");
+/// Closes an account owned by this program, sending its lamports to
+/// `receiver`. The system program cannot debit an account holding data, so
+/// the lamports are moved directly. The data is zeroed before the account is
+/// shrunk and handed back to the system program, so nothing later in the same
+/// transaction can read it as a live account.
+pub fn close_program_account(account: &AccountInfo, receiver: &AccountInfo) -> ProgramResult {
+    let lamports = account.lamports();
+    **receiver.try_borrow_mut_lamports()? = receiver
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    **account.try_borrow_mut_lamports()? = 0;
+
+    account.data.borrow_mut().fill(0);
+    account.resize(0)?;
+    account.assign(&solana_program::system_program::id());
 
     Ok(())
 }
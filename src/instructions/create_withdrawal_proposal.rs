@@ -0,0 +1,127 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::VaultError,
+    events::{ProposalCreated, VaultEvent},
+    instructions::initialize::create_pda_account,
+    state::{
+        account_type::AccountType,
+        vault_state::VaultState,
+        withdrawal_proposal::{ProposalStatus, WithdrawalProposal},
+    },
+};
+
+pub fn create_withdrawal_proposal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+    destination: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let proposer = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let proposal_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if !proposer.is_signer {
+        return Err(VaultError::MissingRequiredSignature.into());
+    }
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    if !vault_state.is_multisig() {
+        return Err(VaultError::NotMultisigVault.into());
+    }
+
+    // timelocked vaults only pay out through RequestWithdraw
+    if vault_state.is_timelocked() {
+        return Err(VaultError::WithdrawTimelocked.into());
+    }
+
+    vault_state.check_not_pooled()?;
+
+    let signer_index = vault_state
+        .signer_index(proposer.key)
+        .ok_or(VaultError::NotASigner)?;
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(VaultError::InvalidSystemProgram.into());
+    }
+
+    let proposal_id = vault_state.proposal_count;
+    let proposal_id_bytes = proposal_id.to_le_bytes();
+    let generation_bytes = vault_state.generation.to_le_bytes();
+
+    let (expected_proposal_pda, bump) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            vault_state_pda.key.as_ref(),
+            &generation_bytes,
+            &proposal_id_bytes,
+        ],
+        program_id,
+    );
+
+    if proposal_account.key != &expected_proposal_pda {
+        return Err(VaultError::InvalidProposal.into());
+    }
+
+    create_pda_account(
+        proposer,
+        proposal_account,
+        WithdrawalProposal::LEN,
+        program_id,
+        system_program,
+        &[
+            b"proposal",
+            vault_state_pda.key.as_ref(),
+            &generation_bytes,
+            &proposal_id_bytes,
+            &[bump],
+        ],
+    )?;
+
+    let mut proposal = WithdrawalProposal {
+        account_type: AccountType::WithdrawalProposal,
+        vault_state: *vault_state_pda.key,
+        proposal_id,
+        proposer: *proposer.key,
+        amount,
+        destination,
+        approvals: 0,
+        rejections: 0,
+        status: ProposalStatus::Pending,
+        bump,
+    };
+
+    // proposing counts as the proposer's approval
+    proposal.record_vote(signer_index, true);
+    proposal.pack(&mut proposal_account.data.borrow_mut())?;
+
+    vault_state.proposal_count = proposal_id
+        .checked_add(1)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    VaultEvent::ProposalCreated(ProposalCreated {
+        vault_state: *vault_state_pda.key,
+        proposal_id,
+        proposer: *proposer.key,
+        amount,
+        destination,
+    })
+    .emit()?;
+
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::VaultError,
+    events::{DelegatedWithdrawal, VaultEvent},
+    instructions::withdraw::{
+        check_allowed_destination, check_withdrawable, transfer_from_vault, VaultTransferAccounts,
+    },
+    state::{delegate::Delegate, vault_state::VaultState},
+};
+
+pub fn delegated_withdraw(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let delegate = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let delegate_account = next_account_info(accounts_iter)?;
+    let source_token_account = next_account_info(accounts_iter)?;
+    let destination_token_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if !delegate.is_signer {
+        return Err(VaultError::MissingRequiredSignature.into());
+    }
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    let token_mint = if vault_state.is_native {
+        None
+    } else {
+        Some(next_account_info(accounts_iter)?)
+    };
+
+    vault_state.check_not_paused()?;
+
+    if vault_state.is_timelocked() {
+        return Err(VaultError::WithdrawTimelocked.into());
+    }
+
+    vault_state.check_not_pooled()?;
+
+    if delegate_account.owner != program_id {
+        return Err(VaultError::InvalidDelegate.into());
+    }
+
+    let mut delegation = Delegate::unpack(&delegate_account.data.borrow())?;
+
+    delegation.verify_pda(
+        delegate_account.key,
+        vault_state_pda.key,
+        vault_state.generation,
+        program_id,
+    )?;
+
+    if &delegation.delegate != delegate.key {
+        return Err(VaultError::InvalidDelegate.into());
+    }
+
+    let clock = Clock::get()?;
+
+    if delegation.is_expired(clock.unix_timestamp) {
+        return Err(VaultError::DelegateExpired.into());
+    }
+
+    if vault_state.allowlist_enabled {
+        check_allowed_destination(
+            program_id,
+            vault_state_pda.key,
+            vault_state.generation,
+            next_account_info(accounts_iter)?,
+            destination_token_account.key,
+            clock.unix_timestamp,
+        )?;
+    }
+
+    delegation.allowance = delegation
+        .allowance
+        .checked_sub(amount)
+        .ok_or(VaultError::AllowanceExceeded)?;
+    delegation.pack(&mut delegate_account.data.borrow_mut())?;
+
+    check_withdrawable(&vault_state, source_token_account, amount)?;
+
+    vault_state.consume_rate_limit(amount, &clock)?;
+    vault_state
+        .stats
+        .record_withdrawal(amount, clock.unix_timestamp)?;
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    transfer_from_vault(
+        &vault_state,
+        &VaultTransferAccounts {
+            vault_state_pda,
+            source_token_account,
+            destination_token_account,
+            token_mint,
+            token_program,
+            system_program,
+            transfer_hook_accounts: accounts,
+        },
+        amount,
+        None,
+    )?;
+
+    VaultEvent::DelegatedWithdrawal(DelegatedWithdrawal {
+        vault_state: *vault_state_pda.key,
+        delegate: *delegate.key,
+        amount,
+        remaining_allowance: delegation.allowance,
+    })
+    .emit()?;
+
+    Ok(())
+}
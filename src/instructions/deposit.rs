@@ -1,19 +1,46 @@
-use borsh::BorshDeserialize;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    clock::Clock,
     entrypoint::ProgramResult,
-    msg,
-    program::invoke,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
-    program_pack::Pack,
     pubkey::Pubkey,
     system_instruction::transfer,
+    sysvar::Sysvar,
+};
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    instruction::{mint_to, sync_native},
+    onchain::invoke_transfer_checked,
+    state::{Account as TokenAccount, Mint},
+};
+
+use crate::{
+    error::VaultError,
+    events::{AssetDeposited, Deposited, VaultEvent},
+    instructions::{
+        add_asset::next_asset,
+        initialize::create_pda_account,
+        redeem::{shares_for_deposit, vault_assets},
+        withdraw::VaultTransferAccounts,
+    },
+    state::{
+        account_type::AccountType, depositor_receipt::DepositorReceipt, vault_state::VaultState,
+    },
 };
-use spl_token::state::Account as TokenAccount;
 
-use crate::state::vault_state::VaultState;
+pub fn deposit(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    process_deposit(program_id, accounts, amount, false)
+}
 
-pub fn deposit(_program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+/// Shared by `Deposit` and `DepositWithReceipt`; `with_receipt` says whether
+/// the depositor's receipt follows the share accounts.
+pub fn process_deposit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+    with_receipt: bool,
+) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
 
     let depositor = next_account_info(accounts_iter)?;
@@ -24,18 +51,82 @@ pub fn deposit(_program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> P
     let system_program = next_account_info(accounts_iter)?;
 
     if !depositor.is_signer {
-        return Err(ProgramError::MissingRequiredSignature);
+        return Err(VaultError::MissingRequiredSignature.into());
     }
 
-    let vault_state = VaultState::try_from_slice(&vault_state_pda.data.borrow())?;
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    vault_state.check_not_paused()?;
+
+    // deposits of a registered asset only update the asset's slot: they mint
+    // no shares and leave the receipts and stats of the vault's own token alone
+    if vault_state.uses_asset_slot(destination_token_account.key) {
+        let (token_mint, asset_account, mut asset) =
+            next_asset(program_id, vault_state_pda.key, accounts_iter)?;
+
+        if destination_token_account.key != &asset.token_account {
+            return Err(VaultError::InvalidVaultAccount.into());
+        }
+
+        if token_program.key != &asset.token_program {
+            return Err(VaultError::InvalidTokenProgram.into());
+        }
+
+        let received = deposit_tokens(
+            depositor,
+            &VaultTransferAccounts {
+                vault_state_pda,
+                source_token_account,
+                destination_token_account,
+                token_mint: Some(token_mint),
+                token_program,
+                system_program,
+                transfer_hook_accounts: accounts,
+            },
+            amount,
+        )?;
+
+        asset.total_deposited = asset
+            .total_deposited
+            .checked_add(received)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        asset.pack(&mut asset_account.data.borrow_mut())?;
+
+        VaultEvent::AssetDeposited(AssetDeposited {
+            vault_state: *vault_state_pda.key,
+            mint: asset.mint,
+            depositor: *depositor.key,
+            amount: received,
+        })
+        .emit()?;
+
+        return Ok(());
+    }
 
     if destination_token_account.key != &vault_state.token_account {
-        return Err(ProgramError::InvalidAccountData);
+        return Err(VaultError::InvalidVaultAccount.into());
     }
 
-    if vault_state.is_native {
+    if vault_state.refundable && !with_receipt {
+        return Err(VaultError::ReceiptRequired.into());
+    }
+
+    // shares are priced against the assets held before this deposit
+    let assets_before = if vault_state.is_pooled() {
+        vault_assets(&vault_state, destination_token_account)?
+    } else {
+        0
+    };
+
+    let received = if vault_state.is_native {
         if system_program.key != &solana_program::system_program::id() {
-            return Err(ProgramError::IncorrectProgramId);
+            return Err(VaultError::InvalidSystemProgram.into());
         }
 
         invoke(
@@ -45,57 +136,352 @@ pub fn deposit(_program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> P
                 destination_token_account.clone(),
                 system_program.clone(),
             ],
-        )?
-    } else {
-        if token_program.key != &spl_token::id() {
-            return Err(ProgramError::IncorrectProgramId);
-        }
+        )?;
 
-        if source_token_account.owner != token_program.key {
-            return Err(ProgramError::InvalidAccountOwner);
-        }
+        amount
+    } else {
+        let token_mint = next_account_info(accounts_iter)?;
 
-        if destination_token_account.owner != token_program.key {
-            return Err(ProgramError::InvalidAccountOwner);
+        if token_mint.key != &vault_state.token_mint {
+            return Err(VaultError::MintMismatch.into());
         }
 
-        let source_token = TokenAccount::unpack(&source_token_account.data.borrow())?;
+        vault_state.check_token_program(token_program.key)?;
 
-        let destination_token = TokenAccount::unpack(&destination_token_account.data.borrow())?;
+        let transfer_accounts = VaultTransferAccounts {
+            vault_state_pda,
+            source_token_account,
+            destination_token_account,
+            token_mint: Some(token_mint),
+            token_program,
+            system_program,
+            transfer_hook_accounts: accounts,
+        };
 
-        if &source_token.owner != depositor.key {
-            return Err(ProgramError::InvalidAccountOwner);
+        if vault_state.wrap_native {
+            wrap_lamports(depositor, &transfer_accounts, amount)?
+        } else {
+            deposit_tokens(depositor, &transfer_accounts, amount)?
         }
+    };
 
-        if &destination_token.owner != vault_state_pda.key {
-            return Err(ProgramError::InvalidAccountOwner);
+    let shares = if vault_state.is_pooled() {
+        let share_mint = next_account_info(accounts_iter)?;
+        let share_account = next_account_info(accounts_iter)?;
+
+        if share_mint.key != &vault_state.share_mint {
+            return Err(VaultError::InvalidShareMint.into());
         }
 
-        if source_token.mint != destination_token.mint {
-            return Err(ProgramError::InvalidAccountData);
+        vault_state.check_token_program(token_program.key)?;
+
+        let supply = StateWithExtensions::<Mint>::unpack(&share_mint.data.borrow())
+            .map_err(|_| VaultError::InvalidShareMint)?
+            .base
+            .supply;
+        let shares = shares_for_deposit(received, assets_before, supply)?;
+
+        if shares == 0 {
+            return Err(VaultError::ZeroShares.into());
         }
 
-        let transfer_ix = spl_token::instruction::transfer(
+        let mint_ix = mint_to(
             token_program.key,
-            source_token_account.key,
-            destination_token_account.key,
-            depositor.key,
+            share_mint.key,
+            share_account.key,
+            vault_state_pda.key,
             &[],
-            amount,
+            shares,
         )?;
 
-        invoke(
-            &transfer_ix,
+        vault_state.with_signer_seeds(|seeds| {
+            invoke_signed(
+                &mint_ix,
+                &[
+                    share_mint.clone(),
+                    share_account.clone(),
+                    vault_state_pda.clone(),
+                    token_program.clone(),
+                ],
+                &[seeds],
+            )
+        })?;
+
+        shares
+    } else {
+        0
+    };
+
+    let clock = Clock::get()?;
+
+    vault_state
+        .stats
+        .record_deposit(received, clock.unix_timestamp)?;
+
+    // only refundable vaults pay receipts back
+    if with_receipt && vault_state.refundable {
+        vault_state.outstanding_receipts = vault_state
+            .outstanding_receipts
+            .checked_add(received)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+    }
+
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    if with_receipt {
+        let receipt_account = next_account_info(accounts_iter)?;
+
+        record_receipt(
+            program_id,
+            vault_state_pda,
+            vault_state.generation,
+            receipt_account,
+            depositor,
+            system_program,
+            received,
+        )?;
+    }
+
+    VaultEvent::Deposited(Deposited {
+        vault_state: *vault_state_pda.key,
+        depositor: *depositor.key,
+        amount: received,
+        shares,
+    })
+    .emit()?;
+
+    Ok(())
+}
+
+/// Adds `amount` to the depositor's receipt, creating it on first deposit.
+fn record_receipt<'info>(
+    program_id: &Pubkey,
+    vault_state_pda: &AccountInfo<'info>,
+    generation: u64,
+    receipt_account: &AccountInfo<'info>,
+    depositor: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    amount: u64,
+) -> ProgramResult {
+    let mut receipt = if receipt_account.data_is_empty() {
+        if system_program.key != &solana_program::system_program::id() {
+            return Err(VaultError::InvalidSystemProgram.into());
+        }
+
+        let (expected_receipt_pda, bump) = Pubkey::find_program_address(
             &[
-                source_token_account.clone(),
-                destination_token_account.clone(),
-                depositor.clone(),
-                token_program.clone(),
+                b"receipt",
+                vault_state_pda.key.as_ref(),
+                &generation.to_le_bytes(),
+                depositor.key.as_ref(),
+            ],
+            program_id,
+        );
+
+        if receipt_account.key != &expected_receipt_pda {
+            return Err(VaultError::InvalidReceipt.into());
+        }
+
+        create_pda_account(
+            depositor,
+            receipt_account,
+            DepositorReceipt::LEN,
+            program_id,
+            system_program,
+            &[
+                b"receipt",
+                vault_state_pda.key.as_ref(),
+                &generation.to_le_bytes(),
+                depositor.key.as_ref(),
+                &[bump],
             ],
         )?;
-    }
 
-    msg!("Deposited {} tokens", amount);
+        DepositorReceipt {
+            account_type: AccountType::DepositorReceipt,
+            vault_state: *vault_state_pda.key,
+            depositor: *depositor.key,
+            total_deposited: 0,
+            balance: 0,
+            last_deposit_slot: 0,
+            bump,
+        }
+    } else {
+        if receipt_account.owner != program_id {
+            return Err(VaultError::InvalidReceipt.into());
+        }
+
+        let receipt = DepositorReceipt::unpack(&receipt_account.data.borrow())?;
+
+        receipt.verify_pda(
+            receipt_account.key,
+            vault_state_pda.key,
+            generation,
+            program_id,
+        )?;
+
+        if &receipt.depositor != depositor.key {
+            return Err(VaultError::InvalidReceipt.into());
+        }
+
+        receipt
+    };
+
+    receipt.total_deposited = receipt
+        .total_deposited
+        .checked_add(amount)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    receipt.balance = receipt
+        .balance
+        .checked_add(amount)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    receipt.last_deposit_slot = Clock::get()?.slot;
+    receipt.pack(&mut receipt_account.data.borrow_mut())?;
 
     Ok(())
 }
+
+/// Moves `amount` of tokens from the depositor into the vault with
+/// `transfer_checked` and returns how much the vault's account actually
+/// received.
+fn deposit_tokens<'info>(
+    depositor: &AccountInfo<'info>,
+    accounts: &VaultTransferAccounts<'_, 'info>,
+    amount: u64,
+) -> Result<u64, ProgramError> {
+    let VaultTransferAccounts {
+        vault_state_pda,
+        source_token_account,
+        destination_token_account,
+        token_mint,
+        token_program,
+        transfer_hook_accounts,
+        ..
+    } = *accounts;
+    let token_mint = token_mint.ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    if source_token_account.owner != token_program.key {
+        return Err(VaultError::InvalidTokenAccount.into());
+    }
+
+    if destination_token_account.owner != token_program.key {
+        return Err(VaultError::InvalidTokenAccount.into());
+    }
+
+    let source_token =
+        StateWithExtensions::<TokenAccount>::unpack(&source_token_account.data.borrow())
+            .map_err(|_| VaultError::InvalidTokenAccount)?
+            .base;
+
+    let destination_token =
+        StateWithExtensions::<TokenAccount>::unpack(&destination_token_account.data.borrow())
+            .map_err(|_| VaultError::InvalidTokenAccount)?
+            .base;
+
+    if &source_token.owner != depositor.key {
+        return Err(VaultError::InvalidTokenAccountOwner.into());
+    }
+
+    if &destination_token.owner != vault_state_pda.key {
+        return Err(VaultError::InvalidTokenAccountOwner.into());
+    }
+
+    if source_token.mint != destination_token.mint {
+        return Err(VaultError::MintMismatch.into());
+    }
+
+    let decimals = StateWithExtensions::<Mint>::unpack(&token_mint.data.borrow())
+        .map_err(|_| VaultError::MintMismatch)?
+        .base
+        .decimals;
+
+    // resolves a transfer hook's extra accounts from everything passed in
+    invoke_transfer_checked(
+        token_program.key,
+        source_token_account.clone(),
+        token_mint.clone(),
+        destination_token_account.clone(),
+        depositor.clone(),
+        transfer_hook_accounts,
+        amount,
+        decimals,
+        &[],
+    )?;
+
+    // a transfer fee is withheld in the vault's account, so credit what
+    // actually arrived rather than `amount`
+    let balance_after =
+        StateWithExtensions::<TokenAccount>::unpack(&destination_token_account.data.borrow())
+            .map_err(|_| VaultError::InvalidTokenAccount)?
+            .base
+            .amount;
+
+    let received = balance_after
+        .checked_sub(destination_token.amount)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+
+    Ok(received)
+}
+
+/// Moves `amount` lamports from the depositor into a wrapping vault's wSOL
+/// account and syncs its token balance, returning the amount credited.
+fn wrap_lamports<'info>(
+    depositor: &AccountInfo<'info>,
+    accounts: &VaultTransferAccounts<'_, 'info>,
+    amount: u64,
+) -> Result<u64, ProgramError> {
+    let VaultTransferAccounts {
+        vault_state_pda,
+        destination_token_account,
+        token_program,
+        system_program,
+        ..
+    } = *accounts;
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(VaultError::InvalidSystemProgram.into());
+    }
+
+    if destination_token_account.owner != token_program.key {
+        return Err(VaultError::InvalidTokenAccount.into());
+    }
+
+    let destination_token =
+        StateWithExtensions::<TokenAccount>::unpack(&destination_token_account.data.borrow())
+            .map_err(|_| VaultError::InvalidTokenAccount)?
+            .base;
+
+    if &destination_token.owner != vault_state_pda.key {
+        return Err(VaultError::InvalidTokenAccountOwner.into());
+    }
+
+    if !destination_token.is_native() {
+        return Err(VaultError::InvalidTokenAccount.into());
+    }
+
+    invoke(
+        &transfer(depositor.key, destination_token_account.key, amount),
+        &[
+            depositor.clone(),
+            destination_token_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+
+    invoke(
+        &sync_native(token_program.key, destination_token_account.key)?,
+        &[destination_token_account.clone(), token_program.clone()],
+    )?;
+
+    let balance_after =
+        StateWithExtensions::<TokenAccount>::unpack(&destination_token_account.data.borrow())
+            .map_err(|_| VaultError::InvalidTokenAccount)?
+            .base
+            .amount;
+
+    let received = balance_after
+        .checked_sub(destination_token.amount)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+
+    Ok(received)
+}
@@ -0,0 +1,11 @@
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+use crate::instructions::deposit::process_deposit;
+
+pub fn deposit_with_receipt(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    process_deposit(program_id, accounts, amount, true)
+}
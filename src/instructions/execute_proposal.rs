@@ -0,0 +1,128 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::VaultError,
+    events::{ProposalExecuted, VaultEvent},
+    instructions::withdraw::{
+        check_allowed_destination, check_withdrawable, transfer_from_vault, VaultTransferAccounts,
+    },
+    state::{
+        vault_state::VaultState,
+        withdrawal_proposal::{ProposalStatus, WithdrawalProposal},
+    },
+};
+
+pub fn execute_proposal(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let proposal_account = next_account_info(accounts_iter)?;
+    let source_token_account = next_account_info(accounts_iter)?;
+    let destination_token_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    let token_mint = if vault_state.is_native {
+        None
+    } else {
+        Some(next_account_info(accounts_iter)?)
+    };
+
+    vault_state.check_not_paused()?;
+
+    if !vault_state.is_multisig() {
+        return Err(VaultError::NotMultisigVault.into());
+    }
+
+    if vault_state.is_timelocked() {
+        return Err(VaultError::WithdrawTimelocked.into());
+    }
+
+    vault_state.check_not_pooled()?;
+
+    if proposal_account.owner != program_id {
+        return Err(VaultError::InvalidProposal.into());
+    }
+
+    let mut proposal = WithdrawalProposal::unpack(&proposal_account.data.borrow())?;
+
+    proposal.verify_pda(
+        proposal_account.key,
+        vault_state_pda.key,
+        vault_state.generation,
+        program_id,
+    )?;
+
+    if proposal.status != ProposalStatus::Pending {
+        return Err(VaultError::ProposalNotPending.into());
+    }
+
+    if proposal.approval_count() < vault_state.threshold as usize {
+        return Err(VaultError::NotEnoughSigners.into());
+    }
+
+    if destination_token_account.key != &proposal.destination {
+        return Err(VaultError::InvalidDestination.into());
+    }
+
+    let clock = Clock::get()?;
+
+    if vault_state.allowlist_enabled {
+        check_allowed_destination(
+            program_id,
+            vault_state_pda.key,
+            vault_state.generation,
+            next_account_info(accounts_iter)?,
+            destination_token_account.key,
+            clock.unix_timestamp,
+        )?;
+    }
+
+    proposal.status = ProposalStatus::Executed;
+    proposal.pack(&mut proposal_account.data.borrow_mut())?;
+
+    check_withdrawable(&vault_state, source_token_account, proposal.amount)?;
+
+    vault_state.consume_rate_limit(proposal.amount, &clock)?;
+    vault_state
+        .stats
+        .record_withdrawal(proposal.amount, clock.unix_timestamp)?;
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    transfer_from_vault(
+        &vault_state,
+        &VaultTransferAccounts {
+            vault_state_pda,
+            source_token_account,
+            destination_token_account,
+            token_mint,
+            token_program,
+            system_program,
+            transfer_hook_accounts: accounts,
+        },
+        proposal.amount,
+        None,
+    )?;
+
+    VaultEvent::ProposalExecuted(ProposalExecuted {
+        vault_state: *vault_state_pda.key,
+        proposal_id: proposal.proposal_id,
+    })
+    .emit()?;
+
+    Ok(())
+}
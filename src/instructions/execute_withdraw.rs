@@ -0,0 +1,130 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::VaultError,
+    events::{VaultEvent, WithdrawalExecuted},
+    instructions::withdraw::{
+        check_allowed_destination, check_withdrawable, transfer_from_vault, VaultTransferAccounts,
+    },
+    state::{
+        pending_withdrawal::{PendingWithdrawal, WithdrawalStatus},
+        vault_state::VaultState,
+    },
+};
+
+pub fn execute_withdraw(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let pending_withdrawal_account = next_account_info(accounts_iter)?;
+    let source_token_account = next_account_info(accounts_iter)?;
+    let destination_token_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    let token_mint = if vault_state.is_native {
+        None
+    } else {
+        Some(next_account_info(accounts_iter)?)
+    };
+
+    vault_state.check_not_paused()?;
+
+    vault_state.check_not_pooled()?;
+
+    if pending_withdrawal_account.owner != program_id {
+        return Err(VaultError::InvalidWithdrawalRequest.into());
+    }
+
+    let mut pending_withdrawal =
+        PendingWithdrawal::unpack(&pending_withdrawal_account.data.borrow())?;
+
+    pending_withdrawal.verify_pda(
+        pending_withdrawal_account.key,
+        vault_state_pda.key,
+        vault_state.generation,
+        program_id,
+    )?;
+
+    if pending_withdrawal.status != WithdrawalStatus::Pending {
+        return Err(VaultError::WithdrawalNotPending.into());
+    }
+
+    let unlocks_at = pending_withdrawal
+        .requested_at
+        .checked_add(vault_state.withdraw_delay)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+
+    let clock = Clock::get()?;
+
+    if clock.unix_timestamp < unlocks_at {
+        return Err(VaultError::TimelockNotElapsed.into());
+    }
+
+    if destination_token_account.key != &pending_withdrawal.destination {
+        return Err(VaultError::InvalidDestination.into());
+    }
+
+    if vault_state.allowlist_enabled {
+        check_allowed_destination(
+            program_id,
+            vault_state_pda.key,
+            vault_state.generation,
+            next_account_info(accounts_iter)?,
+            destination_token_account.key,
+            clock.unix_timestamp,
+        )?;
+    }
+
+    pending_withdrawal.status = WithdrawalStatus::Executed;
+    pending_withdrawal.pack(&mut pending_withdrawal_account.data.borrow_mut())?;
+
+    check_withdrawable(
+        &vault_state,
+        source_token_account,
+        pending_withdrawal.amount,
+    )?;
+
+    vault_state.consume_rate_limit(pending_withdrawal.amount, &clock)?;
+    vault_state
+        .stats
+        .record_withdrawal(pending_withdrawal.amount, clock.unix_timestamp)?;
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    transfer_from_vault(
+        &vault_state,
+        &VaultTransferAccounts {
+            vault_state_pda,
+            source_token_account,
+            destination_token_account,
+            token_mint,
+            token_program,
+            system_program,
+            transfer_hook_accounts: accounts,
+        },
+        pending_withdrawal.amount,
+        None,
+    )?;
+
+    VaultEvent::WithdrawalExecuted(WithdrawalExecuted {
+        vault_state: *vault_state_pda.key,
+        request_id: pending_withdrawal.request_id,
+    })
+    .emit()?;
+
+    Ok(())
+}
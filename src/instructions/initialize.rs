@@ -1,20 +1,44 @@
-use borsh::BorshSerialize;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    clock::Clock,
     entrypoint::ProgramResult,
-    msg,
     program::{invoke, invoke_signed},
     program_error::ProgramError,
+    program_pack::Pack,
     pubkey::Pubkey,
     rent::Rent,
-    system_instruction::{self, create_account},
+    system_instruction::{allocate, assign, create_account, transfer},
     sysvar::Sysvar,
 };
 use spl_associated_token_account::{
-    get_associated_token_address, instruction::create_associated_token_account,
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use spl_token_2022::{
+    check_spl_token_program_account,
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    instruction::initialize_mint2,
+    state::Mint,
+};
+
+use crate::{
+    error::VaultError,
+    events::{VaultEvent, VaultInitialized},
+    instructions::{redeem::SHARE_DECIMALS_OFFSET, VaultConfig},
+    state::{
+        account_type::AccountType,
+        closed_vault::ClosedVault,
+        vault_state::{RateLimit, VaultState, VaultStats, MAX_SIGNERS, VAULT_STATE_RESERVED},
+    },
 };
 
-use crate::state::vault_state::VaultState;
+/// Mint extensions rejected unless the vault opts in with
+/// `allow_unsafe_extensions`: a permanent delegate can move the vault's tokens
+/// without the authority, and non-transferable tokens could never leave it.
+const UNSAFE_MINT_EXTENSIONS: [ExtensionType; 2] = [
+    ExtensionType::PermanentDelegate,
+    ExtensionType::NonTransferable,
+];
 
 pub fn initialize(
     program_id: &Pubkey,
@@ -22,6 +46,8 @@ pub fn initialize(
     vault_bump: u8,
     state_bump: u8,
     is_native: bool,
+    vault_id: u64,
+    config: VaultConfig,
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
 
@@ -34,57 +60,128 @@ pub fn initialize(
     let associated_token_program = next_account_info(accounts_iter)?;
 
     if !authority.is_signer {
-        return Err(ProgramError::MissingRequiredSignature);
+        return Err(VaultError::MissingRequiredSignature.into());
     }
 
-    let (expected_vault_state_pda, _) =
-        Pubkey::find_program_address(&[b"state", authority.key.as_ref()], program_id);
+    let signer_count = config.signers.len();
 
-    if vault_state.key != &expected_vault_state_pda {
-        return Err(ProgramError::InvalidAccountData);
+    if signer_count > MAX_SIGNERS
+        || (config.threshold as usize) > signer_count
+        || (signer_count > 0 && config.threshold == 0)
+    {
+        return Err(VaultError::InvalidMultisigConfig.into());
     }
 
-    // Verify vault state account is uninitialized
-    if vault_state.data_len() > 0 || vault_state.owner == program_id {
-        return Err(ProgramError::AccountAlreadyInitialized);
+    if config.pooled && config.refundable {
+        return Err(VaultError::InvalidVaultConfig.into());
     }
 
-    let space_required = VaultState::space();
-    let min_lamports = Rent::get()?.minimum_balance(space_required);
+    // a wrapping vault is an SPL vault for its token program's native mint
+    if config.wrap_native
+        && (is_native
+            || (token_mint.key != &spl_token::native_mint::id()
+                && token_mint.key != &spl_token_2022::native_mint::id()))
+    {
+        return Err(VaultError::InvalidVaultConfig.into());
+    }
 
-    invoke_signed(
-        &system_instruction::create_account(
-            authority.key,
-            vault_state.key,
-            min_lamports,
-            space_required as u64,
-            program_id,
-        ),
-        &[
-            authority.clone(),
-            vault_state.clone(),
-            system_program.clone(),
-        ],
-        &[&[b"state", authority.key.as_ref(), &[state_bump]]],
-    )?;
+    if config.withdraw_delay < 0 {
+        return Err(VaultError::InvalidWithdrawDelay.into());
+    }
 
-    if !is_native {
-        if token_program.key != &spl_token::id() {
-            return Err(ProgramError::IncorrectProgramId);
+    // the token account and share mint may live under either SPL Token or
+    // Token-2022; native vaults without shares never touch a token program
+    let uses_token_program = !is_native || config.pooled;
+
+    if uses_token_program {
+        check_spl_token_program_account(token_program.key)
+            .map_err(|_| VaultError::InvalidTokenProgram)?;
+    }
+
+    let mut signers = [Pubkey::default(); MAX_SIGNERS];
+
+    for (index, signer) in config.signers.iter().enumerate() {
+        if config.signers[..index].contains(signer) {
+            return Err(VaultError::InvalidMultisigConfig.into());
+        }
+
+        signers[index] = *signer;
+    }
+
+    let vault_id_bytes = vault_id.to_le_bytes();
+
+    let (expected_vault_state_pda, expected_state_bump) = Pubkey::find_program_address(
+        &[b"state", authority.key.as_ref(), &vault_id_bytes],
+        program_id,
+    );
+
+    // the stored bump signs for the vault from here on, and a reopened vault
+    // never signs with it here, so it has to be the canonical one
+    if vault_state.key != &expected_vault_state_pda || state_bump != expected_state_bump {
+        return Err(VaultError::InvalidStatePda.into());
+    }
+
+    // Verify vault state account is uninitialized. `Close` leaves a
+    // `ClosedVault` tombstone behind, so a closed vault's address is reopened
+    // as the next generation, whose derived accounts start out empty
+    let generation = if vault_state.owner == program_id {
+        let closed = ClosedVault::unpack(&vault_state.data.borrow())
+            .map_err(|_| VaultError::AlreadyInitialized)?;
+
+        reopen_account(authority, vault_state, VaultState::space(), system_program)?;
+
+        closed
+            .generation
+            .checked_add(1)
+            .ok_or(VaultError::ArithmeticOverflow)?
+    } else {
+        if vault_state.data_len() > 0 {
+            return Err(VaultError::AlreadyInitialized.into());
         }
 
+        create_pda_account(
+            authority,
+            vault_state,
+            VaultState::space(),
+            program_id,
+            system_program,
+            &[
+                b"state",
+                authority.key.as_ref(),
+                &vault_id_bytes,
+                &[state_bump],
+            ],
+        )?;
+
+        0
+    };
+    let generation_bytes = generation.to_le_bytes();
+
+    let decimals = if is_native {
+        spl_token::native_mint::DECIMALS
+    } else {
+        mint_decimals(token_mint, token_program, config.allow_unsafe_extensions)?
+    };
+
+    if !is_native {
         if associated_token_program.key != &spl_associated_token_account::id() {
-            return Err(ProgramError::IncorrectProgramId);
+            return Err(VaultError::InvalidAssociatedTokenProgram.into());
         }
 
-        let expected_ata = get_associated_token_address(vault_state.key, token_mint.key);
+        let expected_ata = get_associated_token_address_with_program_id(
+            vault_state.key,
+            token_mint.key,
+            token_program.key,
+        );
 
         if token_account.key != &expected_ata {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(VaultError::InvalidVaultAccount.into());
         }
 
+        // idempotent, since anyone can create the state PDA's ATA for a mint
+        // before the vault is (re)opened
         invoke(
-            &create_associated_token_account(
+            &create_associated_token_account_idempotent(
                 authority.key,
                 vault_state.key,
                 token_mint.key,
@@ -101,43 +198,235 @@ pub fn initialize(
             ],
         )?;
     } else {
-        let (vault_account_pda, _) =
+        let (vault_account_pda, expected_vault_bump) =
             Pubkey::find_program_address(&[b"vault", vault_state.key.as_ref()], program_id);
 
-        if *token_account.key != vault_account_pda {
-            return Err(ProgramError::InvalidAccountData);
+        // an already funded vault PDA is only topped up, so a wrong bump would
+        // not fail until the first withdrawal
+        if *token_account.key != vault_account_pda || vault_bump != expected_vault_bump {
+            return Err(VaultError::InvalidVaultAccount.into());
         }
 
-        invoke_signed(
-            &create_account(
-                authority.key,
-                token_account.key,
-                Rent::get()?.minimum_balance(0),
-                0,
-                system_program.key,
-            ),
+        create_pda_account(
+            authority,
+            token_account,
+            0,
+            system_program.key,
+            system_program,
+            &[b"vault", vault_state.key.as_ref(), &[vault_bump]],
+        )?;
+    }
+
+    let share_mint = if config.pooled {
+        let share_mint = next_account_info(accounts_iter)?;
+
+        let (expected_share_mint, share_mint_bump) = Pubkey::find_program_address(
+            &[b"shares", vault_state.key.as_ref(), &generation_bytes],
+            program_id,
+        );
+
+        if share_mint.key != &expected_share_mint {
+            return Err(VaultError::InvalidShareMint.into());
+        }
+
+        let share_decimals = decimals
+            .checked_add(SHARE_DECIMALS_OFFSET)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        create_pda_account(
+            authority,
+            share_mint,
+            Mint::LEN,
+            token_program.key,
+            system_program,
             &[
-                authority.clone(),
-                token_account.clone(),
-                system_program.clone(),
+                b"shares",
+                vault_state.key.as_ref(),
+                &generation_bytes,
+                &[share_mint_bump],
             ],
-            &[&[b"vault", vault_state.key.as_ref(), &[vault_bump]]],
-        )?
-    }
+        )?;
+
+        // the state PDA is the only mint authority, so shares can only be
+        // minted by deposits
+        invoke(
+            &initialize_mint2(
+                token_program.key,
+                share_mint.key,
+                vault_state.key,
+                None,
+                share_decimals,
+            )?,
+            &[share_mint.clone(), token_program.clone()],
+        )?;
+
+        *share_mint.key
+    } else {
+        Pubkey::default()
+    };
+
+    let now = Clock::get()?.unix_timestamp;
 
     let vault_state_data = VaultState {
+        account_type: AccountType::VaultState,
+        version: VaultState::VERSION,
         authority: *authority.key,
         token_mint: *token_mint.key,
         token_account: *token_account.key,
         vault_bump,
         state_bump,
         is_native,
+        vault_id,
+        creator: *authority.key,
+        pending_authority: Pubkey::default(),
+        signer_count: signer_count as u8,
+        threshold: config.threshold,
+        signers,
+        proposal_count: 0,
+        withdraw_delay: config.withdraw_delay,
+        guardian: config.guardian,
+        withdrawal_request_count: 0,
+        rate_limit: RateLimit::default(),
+        window_start: 0,
+        window_used: 0,
+        pending_rate_limit: RateLimit::default(),
+        pending_rate_limit_at: 0,
+        paused: false,
+        allowlist_enabled: false,
+        share_mint,
+        refundable: config.refundable,
+        stats: VaultStats {
+            created_at: now,
+            last_activity_ts: now,
+            ..VaultStats::default()
+        },
+        token_program: if uses_token_program {
+            *token_program.key
+        } else {
+            Pubkey::default()
+        },
+        decimals,
+        asset_count: 0,
+        wrap_native: config.wrap_native,
+        legacy_address: false,
+        outstanding_receipts: 0,
+        generation,
+        reserved: [0; VAULT_STATE_RESERVED],
     };
 
-    let mut account_data = &mut vault_state.data.borrow_mut()[..];
-    vault_state_data.serialize(&mut account_data)?;
+    vault_state_data.pack(&mut vault_state.data.borrow_mut())?;
+
+    VaultEvent::VaultInitialized(VaultInitialized {
+        vault_state: *vault_state.key,
+        authority: *authority.key,
+        token_mint: *token_mint.key,
+        is_native,
+        vault_id,
+    })
+    .emit()?;
+
+    Ok(())
+}
+
+/// Decimals of a mint the vault is about to hold, after checking it belongs to
+/// `token_program` and carries none of `UNSAFE_MINT_EXTENSIONS` unless allowed.
+pub fn mint_decimals(
+    token_mint: &AccountInfo,
+    token_program: &AccountInfo,
+    allow_unsafe_extensions: bool,
+) -> Result<u8, ProgramError> {
+    if token_mint.owner != token_program.key {
+        return Err(VaultError::MintMismatch.into());
+    }
+
+    let mint_data = token_mint.data.borrow();
+    let mint =
+        StateWithExtensions::<Mint>::unpack(&mint_data).map_err(|_| VaultError::MintMismatch)?;
+
+    if !allow_unsafe_extensions
+        && mint
+            .get_extension_types()?
+            .iter()
+            .any(|extension| UNSAFE_MINT_EXTENSIONS.contains(extension))
+    {
+        return Err(VaultError::UnsupportedMintExtension.into());
+    }
+
+    Ok(mint.base.decimals)
+}
+
+/// Grows the `ClosedVault` tombstone at `account` back to `space` bytes, with
+/// `payer` covering the extra rent.
+fn reopen_account<'info>(
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    space: usize,
+    system_program: &AccountInfo<'info>,
+) -> ProgramResult {
+    let shortfall = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(account.lamports());
+
+    if shortfall > 0 {
+        invoke(
+            &transfer(payer.key, account.key, shortfall),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+
+    account.resize(space)?;
+    account.data.borrow_mut().fill(0);
+
+    Ok(())
+}
+
+/// Creates the PDA `account` with `space` bytes owned by `owner`, paid for by
+/// `payer`. `create_account` fails on an address that already holds lamports,
+/// which anyone can arrange by sending SOL to it (for example to a closed
+/// vault's address), so such an account is topped up, allocated and assigned
+/// instead.
+pub fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    space: usize,
+    owner: &Pubkey,
+    system_program: &AccountInfo<'info>,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let rent_exempt = Rent::get()?.minimum_balance(space);
+
+    if account.lamports() == 0 {
+        return invoke_signed(
+            &create_account(payer.key, account.key, rent_exempt, space as u64, owner),
+            &[payer.clone(), account.clone(), system_program.clone()],
+            &[signer_seeds],
+        );
+    }
 
-    msg!("Vault initialized: {:?}", vault_state.key);
+    let shortfall = rent_exempt.saturating_sub(account.lamports());
+
+    if shortfall > 0 {
+        invoke(
+            &transfer(payer.key, account.key, shortfall),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+
+    if space > 0 {
+        invoke_signed(
+            &allocate(account.key, space as u64),
+            &[account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+    }
+
+    if owner != system_program.key {
+        invoke_signed(
+            &assign(account.key, owner),
+            &[account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+    }
 
     Ok(())
 }
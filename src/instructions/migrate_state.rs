@@ -0,0 +1,114 @@
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program::invoke,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::transfer,
+    sysvar::Sysvar,
+};
+use spl_token_2022::{extension::StateWithExtensions, state::Mint};
+
+use crate::{
+    error::VaultError,
+    events::{StateMigrated, VaultEvent},
+    state::vault_state::{LegacyVaultState, OriginalVaultState, VaultState},
+};
+
+pub fn migrate_state(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let authority = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if !authority.is_signer {
+        return Err(VaultError::MissingRequiredSignature.into());
+    }
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(VaultError::InvalidSystemProgram.into());
+    }
+
+    let mut vault_state = match vault_state_pda.data_len() {
+        VaultState::LEN => {
+            let data = vault_state_pda.data.borrow();
+
+            if data.get(1) == Some(&VaultState::VERSION) {
+                // SPL vaults created before `decimals` was recorded read 0 and
+                // may still be migrated to backfill it
+                let state = VaultState::unpack(&data)?;
+
+                if state.is_native || state.decimals != 0 {
+                    return Err(VaultError::AlreadyMigrated.into());
+                }
+
+                state
+            } else {
+                VaultState::unpack_outdated(&data)?
+            }
+        }
+        LegacyVaultState::LEN => LegacyVaultState::try_from_slice(&vault_state_pda.data.borrow())
+            .map_err(|_| VaultError::InvalidStateData)?
+            .into_current(),
+        OriginalVaultState::LEN => {
+            OriginalVaultState::try_from_slice(&vault_state_pda.data.borrow())
+                .map_err(|_| VaultError::InvalidStateData)?
+                .into_current()
+        }
+        _ => return Err(VaultError::InvalidStateData.into()),
+    };
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    if &vault_state.authority != authority.key {
+        return Err(VaultError::Unauthorized.into());
+    }
+
+    if !vault_state.is_native {
+        let token_mint = next_account_info(accounts_iter)?;
+
+        if token_mint.key != &vault_state.token_mint
+            || token_mint.owner != &vault_state.token_program_id()
+        {
+            return Err(VaultError::MintMismatch.into());
+        }
+
+        vault_state.decimals = StateWithExtensions::<Mint>::unpack(&token_mint.data.borrow())
+            .map_err(|_| VaultError::MintMismatch)?
+            .base
+            .decimals;
+    }
+
+    // top up rent for the larger account before growing it; a no-op for
+    // accounts that already have the current size
+    let min_lamports = Rent::get()?.minimum_balance(VaultState::LEN);
+    let shortfall = min_lamports.saturating_sub(vault_state_pda.lamports());
+
+    if shortfall > 0 {
+        invoke(
+            &transfer(authority.key, vault_state_pda.key, shortfall),
+            &[
+                authority.clone(),
+                vault_state_pda.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+
+    vault_state_pda.resize(VaultState::LEN)?;
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    VaultEvent::StateMigrated(StateMigrated {
+        vault_state: *vault_state_pda.key,
+        version: VaultState::VERSION,
+    })
+    .emit()?;
+
+    Ok(())
+}
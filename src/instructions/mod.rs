@@ -1,53 +1,398 @@
+pub mod accept_authority;
+pub mod add_allowed_destination;
+pub mod add_asset;
+pub mod approve_delegate;
+pub mod approve_proposal;
+pub mod cancel_withdraw;
 pub mod close;
+pub mod create_withdrawal_proposal;
+pub mod delegated_withdraw;
 pub mod deposit;
+pub mod deposit_with_receipt;
+pub mod execute_proposal;
+pub mod execute_withdraw;
 pub mod initialize;
+pub mod migrate_state;
+pub mod pause;
+pub mod propose_authority;
+pub mod redeem;
+pub mod reject_proposal;
+pub mod remove_allowed_destination;
+pub mod remove_asset;
+pub mod request_withdraw;
+pub mod revoke_delegate;
+pub mod set_rate_limit;
+pub mod unpause;
 pub mod withdraw;
+pub mod withdraw_all;
+pub mod withdraw_own;
 
 use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+
+use crate::state::vault_state::RateLimit;
+
+/// Optional settings chosen when a vault is created.
+#[derive(BorshDeserialize, BorshSerialize, Debug, Clone, Default)]
+pub struct VaultConfig {
+    /// Keys allowed to approve authority actions. Empty for a single-signer
+    /// vault controlled by the authority key alone.
+    pub signers: Vec<Pubkey>,
+    /// How many of `signers` must sign; 0 when `signers` is empty.
+    pub threshold: u8,
+    /// Seconds between `RequestWithdraw` and `ExecuteWithdraw`. When non-zero,
+    /// instant `Withdraw` is disabled.
+    pub withdraw_delay: i64,
+    /// Key allowed to cancel requested withdrawals alongside the authority.
+    /// `Pubkey::default()` for none.
+    pub guardian: Pubkey,
+    /// Create a share mint so deposits mint shares redeemable with `Redeem`.
+    pub pooled: bool,
+    /// Require depositor receipts and let depositors reclaim them with
+    /// `WithdrawOwn`. Cannot be combined with `pooled`.
+    pub refundable: bool,
+    /// Accept a Token-2022 mint with extensions that let a third party move or
+    /// lock the vault's tokens, such as a permanent delegate.
+    pub allow_unsafe_extensions: bool,
+    /// Hold SOL as wrapped SOL in an SPL vault for the native mint. Deposits
+    /// take lamports and sync them into the vault's wSOL account, and
+    /// `Withdraw` can unwrap back to lamports.
+    pub wrap_native: bool,
+}
 
 #[derive(BorshDeserialize, BorshSerialize, Debug, Clone)]
 pub enum VaultInstruction {
     /// Initialize a new vault
+    /// The state PDA is derived from `[b"state", authority, vault_id]`, so one
+    /// authority can own several vaults by picking distinct ids. A closed
+    /// vault's address can be initialized again, with any mint, as the next
+    /// generation of the vault. `state_bump` and, for native vaults,
+    /// `vault_bump` must be the canonical bumps of their PDAs.
     /// Accounts required:
     /// 0. [signer] Authority (who will control the vault)
     /// 1. [writable] Vault state account (PDA)
     /// 2. [] Token mint (if SPL token vault, else can be system program)
     /// 3. [writable] Token account (ATA for holding tokens)
-    /// 4. [] Token program (if SPL token)
+    /// 4. [] Token program (SPL Token or Token-2022; SPL vaults and pooled vaults)
     /// 5. [] System program
+    /// 6. [] Associated token program
+    /// 7. [writable] Share mint (PDA of `[b"shares", vault_state, generation]`, pooled vaults only)
     Initialize {
         vault_bump: u8,
         state_bump: u8,
         is_native: bool,
+        vault_id: u64,
+        config: VaultConfig,
     },
 
-    /// Deposit tokens into the vault
+    /// Deposit tokens into the vault. The vault is credited with what its token
+    /// account actually received, net of any Token-2022 transfer fee. Passing
+    /// one of the vault's asset token accounts as the destination deposits
+    /// that asset instead: accounts 6 and 7 are then the asset's mint and
+    /// slot, and no shares or receipts are involved. `wrap_native` vaults take
+    /// `amount` lamports from the depositor and sync them into their wSOL
+    /// account. Refundable vaults only accept `DepositWithReceipt`.
+    /// Accounts a Token-2022 transfer hook needs go after the ones listed below.
     /// Accounts expected:
     /// 0. [signer] Depositor
     /// 1. [writable] Vault state account
-    /// 2. [writable] Depositor's token account (source)
+    /// 2. [writable] Depositor's token account (source; unused for native and `wrap_native` vaults)
     /// 3. [writable] Vault's token account (destination)
-    /// 4. [] Token program (if SPL token)
+    /// 4. [] Token program (the vault's SPL Token or Token-2022 program)
     /// 5. [] System program (if native SOL)
+    /// 6. [] Vault's token mint (SPL vaults only)
+    /// 7. [writable] Share mint (pooled vaults only)
+    /// 8. [writable] Depositor's share token account (pooled vaults only)
     Deposit { amount: u64 },
 
-    /// Withdraw tokens from the vault
+    /// Withdraw tokens from the vault. Not available on timelocked vaults; use
+    /// `RequestWithdraw` / `ExecuteWithdraw` instead. Pooled vaults only pay
+    /// out their own token through `Redeem`. Passing one of the vault's
+    /// asset token accounts as the source withdraws that asset: accounts 6 and
+    /// 7 are then the asset's mint and slot, and the rest shift by one. A
+    /// `wrap_native` vault given the authority's wallet as the destination
+    /// unwraps and pays lamports: accounts 7 and 8 are then the writable unwrap
+    /// account (PDA of `[b"unwrap", vault_state]`) and a signer, writable payer
+    /// that fronts its rent, and the rest shift by two. Native vaults keep their
+    /// rent-exempt minimum, and refundable vaults keep what their receipts
+    /// still owe, so withdrawing more fails with `InsufficientVaultBalance`.
+    /// Accounts a Token-2022 transfer hook needs go after the ones listed below.
     /// Accounts expected:
-    /// 0. [signer] Authority (must be vault authority)
+    /// 0. [signer] Authority (must be vault authority, need not sign for multisig vaults)
     /// 1. [writable] Vault state account
     /// 2. [writable] Vault's token account (source)
     /// 3. [writable] Recipient's token account (destination)
-    /// 4. [] Token program (if SPL token)
+    /// 4. [] Token program (the vault's SPL Token or Token-2022 program)
     /// 5. [] System program (if native SOL)
+    /// 6. [] Vault's token mint (SPL vaults only)
+    /// 7. [] Destination allowlist (only when the vault's allowlist is enabled)
+    /// 8. [signer] Multisig signers, one trailing account each (multisig vaults only)
     Withdraw { amount: u64 },
 
-    /// Close the vault and reclaim rent
+    /// Close the vault and reclaim rent. Tokens still in an SPL vault are swept
+    /// to the authority's token account first. The sweep is checked like a
+    /// `Withdraw`, and pooled or refundable vaults only close once every share
+    /// is redeemed and every receipt refunded.
+    /// Accounts a Token-2022 transfer hook needs go after the ones listed below.
     /// Accounts expected:
-    /// 0. [signer] Authority (must be vault authority)
+    /// 0. [signer, writable] Authority (must be vault authority, need not sign for multisig vaults)
     /// 1. [writable] Vault state account
     /// 2. [writable] Vault's token account (to close)
-    /// 3. [writable] Authority's token account (to receive tokens)
-    /// 4. [] Token program (if SPL token)
+    /// 3. [writable] Authority's token account (to receive tokens; owned by the authority and
+    ///    holding the vault's mint for SPL vaults)
+    /// 4. [] Token program (the vault's SPL Token or Token-2022 program)
     /// 5. [] System program
+    /// 6. [] Vault's token mint (SPL vaults only)
+    /// 7. [] Share mint (pooled vaults only)
+    /// 8. [] Destination allowlist (only when the vault's allowlist is enabled)
+    /// 9. [signer] Multisig signers, one trailing account each (multisig vaults only)
     Close,
+
+    /// Upgrade a vault state account written with the original or unversioned
+    /// layout, or an older `VaultState` version, to the current layout, growing
+    /// it and topping up its rent when needed. SPL vaults get their `decimals`
+    /// from the mint, which also lets current vaults that predate the field
+    /// backfill it
+    /// Accounts expected:
+    /// 0. [signer, writable] Authority (must be vault authority, pays the extra rent)
+    /// 1. [writable] Vault state account
+    /// 2. [] System program
+    /// 3. [] Vault's token mint (SPL vaults only)
+    MigrateState,
+
+    /// Propose a new vault authority; it takes effect once accepted.
+    /// Proposing `Pubkey::default()` cancels a pending proposal.
+    /// Accounts expected:
+    /// 0. [signer] Authority (must be vault authority, need not sign for multisig vaults)
+    /// 1. [writable] Vault state account
+    /// 2. [signer] Multisig signers, one trailing account each (multisig vaults only)
+    ProposeAuthority { new_authority: Pubkey },
+
+    /// Accept a pending authority proposal
+    /// Accounts expected:
+    /// 0. [signer] Pending authority
+    /// 1. [writable] Vault state account
+    AcceptAuthority,
+
+    /// Propose a withdrawal from a multisig vault; the proposer's vote counts
+    /// as the first approval. Not available on timelocked vaults
+    /// Accounts expected:
+    /// 0. [signer, writable] Proposer (member of the signer set, pays rent)
+    /// 1. [writable] Vault state account
+    /// 2. [writable] Proposal account (PDA of `[b"proposal", vault_state, generation, proposal_count]`)
+    /// 3. [] System program
+    CreateWithdrawalProposal { amount: u64, destination: Pubkey },
+
+    /// Approve a pending withdrawal proposal
+    /// Accounts expected:
+    /// 0. [signer] Member of the signer set
+    /// 1. [] Vault state account
+    /// 2. [writable] Proposal account
+    ApproveProposal,
+
+    /// Reject a pending withdrawal proposal; it is closed as rejected once the
+    /// threshold can no longer be reached
+    /// Accounts expected:
+    /// 0. [signer] Member of the signer set
+    /// 1. [] Vault state account
+    /// 2. [writable] Proposal account
+    RejectProposal,
+
+    /// Execute an approved withdrawal proposal; anyone may submit it
+    /// Accounts a Token-2022 transfer hook needs go after the ones listed below.
+    /// Accounts expected:
+    /// 0. [writable] Vault state account
+    /// 1. [writable] Proposal account
+    /// 2. [writable] Vault's token account (source)
+    /// 3. [writable] Proposal destination
+    /// 4. [] Token program (the vault's SPL Token or Token-2022 program)
+    /// 5. [] System program (if native SOL)
+    /// 6. [] Vault's token mint (SPL vaults only)
+    /// 7. [] Destination allowlist (only when the vault's allowlist is enabled)
+    ExecuteProposal,
+
+    /// Queue a withdrawal on a timelocked vault
+    /// Accounts expected:
+    /// 0. [signer] Authority (must be vault authority, need not sign for multisig vaults)
+    /// 1. [writable] Vault state account
+    /// 2. [writable] Pending withdrawal account (PDA of `[b"withdrawal", vault_state, generation, withdrawal_request_count]`)
+    /// 3. [signer, writable] Payer for the pending withdrawal account
+    /// 4. [] System program
+    /// 5. [signer] Multisig signers, one trailing account each (multisig vaults only)
+    RequestWithdraw { amount: u64, destination: Pubkey },
+
+    /// Execute a queued withdrawal once the vault's delay has elapsed; anyone
+    /// may submit it
+    /// Accounts a Token-2022 transfer hook needs go after the ones listed below.
+    /// Accounts expected:
+    /// 0. [writable] Vault state account
+    /// 1. [writable] Pending withdrawal account
+    /// 2. [writable] Vault's token account (source)
+    /// 3. [writable] Requested destination
+    /// 4. [] Token program (the vault's SPL Token or Token-2022 program)
+    /// 5. [] System program (if native SOL)
+    /// 6. [] Vault's token mint (SPL vaults only)
+    /// 7. [] Destination allowlist (only when the vault's allowlist is enabled)
+    ExecuteWithdraw,
+
+    /// Cancel a queued withdrawal
+    /// Accounts expected:
+    /// 0. [signer] Authority or guardian (authority need not sign for multisig vaults)
+    /// 1. [] Vault state account
+    /// 2. [writable] Pending withdrawal account
+    /// 3. [signer] Multisig signers, one trailing account each (multisig vaults only)
+    CancelWithdraw,
+
+    /// Set the vault's withdrawal rate limit. Applies immediately on a vault
+    /// without one; otherwise the change is queued for
+    /// `RATE_LIMIT_CHANGE_DELAY` seconds and takes effect on the next
+    /// withdrawal after that.
+    /// Accounts expected:
+    /// 0. [signer] Authority (must be vault authority, need not sign for multisig vaults)
+    /// 1. [writable] Vault state account
+    /// 2. [signer] Multisig signers, one trailing account each (multisig vaults only)
+    SetRateLimit { rate_limit: RateLimit },
+
+    /// Pause the vault. Deposits, withdrawals and executions of proposals or
+    /// queued withdrawals fail until it is unpaused.
+    /// Accounts expected:
+    /// 0. [signer] Authority or guardian (authority need not sign for multisig vaults)
+    /// 1. [writable] Vault state account
+    /// 2. [signer] Multisig signers, one trailing account each (multisig vaults only)
+    Pause,
+
+    /// Unpause the vault; only the authority can do this
+    /// Accounts expected:
+    /// 0. [signer] Authority (must be vault authority, need not sign for multisig vaults)
+    /// 1. [writable] Vault state account
+    /// 2. [signer] Multisig signers, one trailing account each (multisig vaults only)
+    Unpause,
+
+    /// Add a destination to the vault's allowlist, creating and enabling the
+    /// allowlist on first use. Withdrawals accept the destination only after
+    /// `ALLOWLIST_ADDITION_DELAY` seconds.
+    /// Accounts expected:
+    /// 0. [signer] Authority (must be vault authority, need not sign for multisig vaults)
+    /// 1. [writable] Vault state account
+    /// 2. [writable] Allowlist account (PDA of `[b"allowlist", vault_state, generation]`)
+    /// 3. [signer, writable] Payer for the allowlist's rent
+    /// 4. [] System program
+    /// 5. [signer] Multisig signers, one trailing account each (multisig vaults only)
+    AddAllowedDestination { destination: Pubkey },
+
+    /// Remove a destination from the vault's allowlist; takes effect immediately
+    /// Accounts expected:
+    /// 0. [signer] Authority (must be vault authority, need not sign for multisig vaults)
+    /// 1. [] Vault state account
+    /// 2. [writable] Allowlist account
+    /// 3. [signer] Multisig signers, one trailing account each (multisig vaults only)
+    RemoveAllowedDestination { destination: Pubkey },
+
+    /// Let `delegate` withdraw up to `allowance` until `expires_at` (0 for no
+    /// expiry). Approving an existing delegate replaces its allowance and
+    /// expiry.
+    /// Accounts expected:
+    /// 0. [signer] Authority (must be vault authority, need not sign for multisig vaults)
+    /// 1. [] Vault state account
+    /// 2. [writable] Delegate account (PDA of `[b"delegate", vault_state, generation, delegate]`)
+    /// 3. [signer, writable] Payer for the delegate account
+    /// 4. [] System program
+    /// 5. [signer] Multisig signers, one trailing account each (multisig vaults only)
+    ApproveDelegate {
+        delegate: Pubkey,
+        allowance: u64,
+        expires_at: i64,
+    },
+
+    /// Revoke a delegate and close its account
+    /// Accounts expected:
+    /// 0. [signer] Authority (must be vault authority, need not sign for multisig vaults)
+    /// 1. [] Vault state account
+    /// 2. [writable] Delegate account
+    /// 3. [writable] Receiver of the delegate account's rent
+    /// 4. [signer] Multisig signers, one trailing account each (multisig vaults only)
+    RevokeDelegate,
+
+    /// Withdraw on behalf of the vault as an approved delegate, spending from
+    /// its allowance. Not available on timelocked vaults.
+    /// Accounts a Token-2022 transfer hook needs go after the ones listed below.
+    /// Accounts expected:
+    /// 0. [signer] Delegate
+    /// 1. [writable] Vault state account
+    /// 2. [writable] Delegate account
+    /// 3. [writable] Vault's token account (source)
+    /// 4. [writable] Recipient's token account (destination)
+    /// 5. [] Token program (the vault's SPL Token or Token-2022 program)
+    /// 6. [] System program (if native SOL)
+    /// 7. [] Vault's token mint (SPL vaults only)
+    /// 8. [] Destination allowlist (only when the vault's allowlist is enabled)
+    DelegatedWithdraw { amount: u64 },
+
+    /// Burn shares of a pooled vault for their pro-rata share of its assets,
+    /// rounded down
+    /// Accounts a Token-2022 transfer hook needs go after the ones listed below.
+    /// Accounts expected:
+    /// 0. [signer] Share owner
+    /// 1. [writable] Vault state account
+    /// 2. [writable] Share mint
+    /// 3. [writable] Owner's share token account
+    /// 4. [writable] Vault's token account (source)
+    /// 5. [writable] Recipient's token account (destination, owned by the share owner for SPL vaults)
+    /// 6. [] Token program (the vault's SPL Token or Token-2022 program)
+    /// 7. [] System program (if native SOL)
+    /// 8. [] Vault's token mint (SPL vaults only)
+    Redeem { shares: u64 },
+
+    /// Reclaim up to the balance recorded in the depositor's receipt from a
+    /// refundable vault
+    /// Accounts a Token-2022 transfer hook needs go after the ones listed below.
+    /// Accounts expected:
+    /// 0. [signer] Depositor
+    /// 1. [writable] Vault state account
+    /// 2. [writable] Depositor receipt
+    /// 3. [writable] Vault's token account (source)
+    /// 4. [writable] Recipient's token account (destination, owned by the depositor for SPL vaults)
+    /// 5. [] Token program (the vault's SPL Token or Token-2022 program)
+    /// 6. [] System program (if native SOL)
+    /// 7. [] Vault's token mint (SPL vaults only)
+    WithdrawOwn { amount: u64 },
+
+    /// Register `mint` as an extra asset the vault can hold, creating its slot
+    /// and the state PDA's token account for it
+    /// Accounts expected:
+    /// 0. [signer] Authority (must be vault authority, need not sign for multisig vaults)
+    /// 1. [writable] Vault state account
+    /// 2. [writable] Asset slot (PDA of `[b"asset", vault_state, mint]`)
+    /// 3. [] The asset's mint
+    /// 4. [writable] Vault state's associated token account for the mint
+    /// 5. [signer, writable] Payer for the new accounts
+    /// 6. [] Token program (SPL Token or Token-2022, owning the mint)
+    /// 7. [] System program
+    /// 8. [] Associated token program
+    /// 9. [signer] Multisig signers, one trailing account each (multisig vaults only)
+    AddAsset { mint: Pubkey },
+
+    /// Close an empty asset slot and its token account
+    /// Accounts expected:
+    /// 0. [signer] Authority (must be vault authority, need not sign for multisig vaults)
+    /// 1. [writable] Vault state account
+    /// 2. [writable] Asset slot
+    /// 3. [writable] Vault's token account for the asset
+    /// 4. [writable] Receiver of both accounts' rent
+    /// 5. [] Token program (the asset's SPL Token or Token-2022 program)
+    /// 6. [signer] Multisig signers, one trailing account each (multisig vaults only)
+    RemoveAsset { mint: Pubkey },
+
+    /// Withdraw the source account's whole withdrawable balance: everything
+    /// above the vault PDA's rent-exempt minimum for native vaults, or the full
+    /// token balance otherwise, less what a refundable vault's receipts still
+    /// owe. Takes the same accounts as `Withdraw` and fails with
+    /// `InsufficientVaultBalance` when there is nothing to withdraw.
+    WithdrawAll,
+
+    /// `Deposit`, also adding the amount to the depositor's receipt. Required
+    /// for refundable vaults. Takes the accounts of `Deposit`, followed by:
+    /// 9. [writable] Depositor receipt (PDA of `[b"receipt", vault_state, generation, depositor]`;
+    ///    created on first use, the depositor pays its rent)
+    DepositWithReceipt { amount: u64 },
 }
@@ -0,0 +1,43 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::VaultError,
+    events::{Paused, VaultEvent},
+    state::vault_state::VaultState,
+};
+
+pub fn pause(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let pauser = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    // the guardian can pause on its own; anyone else goes through the vault's
+    // normal authority check
+    if !(pauser.is_signer && vault_state.is_guardian(pauser.key)) {
+        vault_state.check_authority(pauser, accounts_iter.as_slice())?;
+    }
+
+    vault_state.paused = true;
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    VaultEvent::Paused(Paused {
+        vault_state: *vault_state_pda.key,
+        paused_by: *pauser.key,
+    })
+    .emit()?;
+
+    Ok(())
+}
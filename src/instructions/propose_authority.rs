@@ -0,0 +1,43 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::VaultError,
+    events::{AuthorityProposed, VaultEvent},
+    state::vault_state::VaultState,
+};
+
+pub fn propose_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_authority: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let authority = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    vault_state.check_authority(authority, accounts_iter.as_slice())?;
+
+    vault_state.pending_authority = new_authority;
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    VaultEvent::AuthorityProposed(AuthorityProposed {
+        vault_state: *vault_state_pda.key,
+        pending_authority: new_authority,
+    })
+    .emit()?;
+
+    Ok(())
+}
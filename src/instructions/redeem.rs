@@ -0,0 +1,209 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    instruction::burn,
+    state::{Account as TokenAccount, Mint},
+};
+
+use crate::{
+    error::VaultError,
+    events::{Redeemed, VaultEvent},
+    instructions::withdraw::{transfer_from_vault, VaultTransferAccounts},
+    state::vault_state::VaultState,
+};
+
+pub fn redeem(program_id: &Pubkey, accounts: &[AccountInfo], shares: u64) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let redeemer = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let share_mint = next_account_info(accounts_iter)?;
+    let share_account = next_account_info(accounts_iter)?;
+    let source_token_account = next_account_info(accounts_iter)?;
+    let destination_token_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if !redeemer.is_signer {
+        return Err(VaultError::MissingRequiredSignature.into());
+    }
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    let token_mint = if vault_state.is_native {
+        None
+    } else {
+        Some(next_account_info(accounts_iter)?)
+    };
+
+    vault_state.check_not_paused()?;
+
+    if !vault_state.is_pooled() {
+        return Err(VaultError::NotPooledVault.into());
+    }
+
+    if share_mint.key != &vault_state.share_mint {
+        return Err(VaultError::InvalidShareMint.into());
+    }
+
+    vault_state.check_token_program(token_program.key)?;
+
+    let supply = StateWithExtensions::<Mint>::unpack(&share_mint.data.borrow())
+        .map_err(|_| VaultError::InvalidShareMint)?
+        .base
+        .supply;
+    let assets = vault_assets(&vault_state, source_token_account)?;
+    let amount = assets_for_shares(shares, assets, supply)?;
+
+    if amount == 0 {
+        return Err(VaultError::ZeroShares.into());
+    }
+
+    invoke(
+        &burn(
+            token_program.key,
+            share_account.key,
+            share_mint.key,
+            redeemer.key,
+            &[],
+            shares,
+        )?,
+        &[
+            share_account.clone(),
+            share_mint.clone(),
+            redeemer.clone(),
+            token_program.clone(),
+        ],
+    )?;
+
+    let clock = Clock::get()?;
+
+    vault_state.consume_rate_limit(amount, &clock)?;
+    vault_state
+        .stats
+        .record_withdrawal(amount, clock.unix_timestamp)?;
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    transfer_from_vault(
+        &vault_state,
+        &VaultTransferAccounts {
+            vault_state_pda,
+            source_token_account,
+            destination_token_account,
+            token_mint,
+            token_program,
+            system_program,
+            transfer_hook_accounts: accounts,
+        },
+        amount,
+        Some(redeemer.key),
+    )?;
+
+    VaultEvent::Redeemed(Redeemed {
+        vault_state: *vault_state_pda.key,
+        owner: *redeemer.key,
+        shares,
+        amount,
+    })
+    .emit()?;
+
+    Ok(())
+}
+
+/// Underlying held by the vault: the token account balance for SPL vaults, or
+/// the vault PDA's lamports above its rent-exempt minimum for native vaults.
+pub fn vault_assets(
+    vault_state: &VaultState,
+    vault_token_account: &AccountInfo,
+) -> Result<u64, ProgramError> {
+    if vault_token_account.key != &vault_state.token_account {
+        return Err(VaultError::InvalidVaultAccount.into());
+    }
+
+    if vault_state.is_native {
+        let rent_exempt = Rent::get()?.minimum_balance(vault_token_account.data_len());
+        return Ok(vault_token_account.lamports().saturating_sub(rent_exempt));
+    }
+
+    if vault_token_account.owner != &vault_state.token_program_id() {
+        return Err(VaultError::InvalidTokenAccount.into());
+    }
+
+    let vault_token =
+        StateWithExtensions::<TokenAccount>::unpack(&vault_token_account.data.borrow())
+            .map_err(|_| VaultError::InvalidTokenAccount)?
+            .base;
+
+    Ok(vault_token.amount)
+}
+
+/// Underlying the authority side can move out: `vault_assets` less what a
+/// refundable vault still owes depositors on their receipts.
+pub fn withdrawable_assets(
+    vault_state: &VaultState,
+    vault_token_account: &AccountInfo,
+) -> Result<u64, ProgramError> {
+    Ok(vault_assets(vault_state, vault_token_account)?
+        .saturating_sub(vault_state.outstanding_receipts))
+}
+
+/// Shares every pool prices as if it always had, backed by `VIRTUAL_ASSETS`.
+/// A first depositor who donates to the vault to inflate the share price
+/// gives most of the donation to these virtual shares, so the attack costs
+/// far more than it can take from the next depositor.
+pub const VIRTUAL_SHARES: u64 = 1_000;
+pub const VIRTUAL_ASSETS: u64 = 1;
+
+/// Decimals share mints carry on top of the underlying's, so one share is
+/// displayed like one unit of underlying at the initial `VIRTUAL_SHARES` rate.
+pub const SHARE_DECIMALS_OFFSET: u8 = 3;
+
+/// Shares minted for depositing `amount` into a pool holding `assets` against
+/// `supply` shares, rounded down.
+pub fn shares_for_deposit(amount: u64, assets: u64, supply: u64) -> Result<u64, ProgramError> {
+    if supply > 0 && assets == 0 {
+        return Err(VaultError::PoolInsolvent.into());
+    }
+
+    mul_div_floor(
+        amount,
+        supply as u128 + VIRTUAL_SHARES as u128,
+        assets as u128 + VIRTUAL_ASSETS as u128,
+    )
+}
+
+/// Underlying paid out for burning `shares` from a pool holding `assets`
+/// against `supply` shares, rounded down.
+pub fn assets_for_shares(shares: u64, assets: u64, supply: u64) -> Result<u64, ProgramError> {
+    if supply == 0 {
+        return Err(VaultError::ZeroShares.into());
+    }
+
+    mul_div_floor(
+        shares,
+        assets as u128 + VIRTUAL_ASSETS as u128,
+        supply as u128 + VIRTUAL_SHARES as u128,
+    )
+}
+
+fn mul_div_floor(a: u64, b: u128, c: u128) -> Result<u64, ProgramError> {
+    (a as u128)
+        .checked_mul(b)
+        .and_then(|product| u64::try_from(product / c).ok())
+        .ok_or_else(|| VaultError::ArithmeticOverflow.into())
+}
@@ -0,0 +1,63 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::VaultError,
+    events::{AllowedDestinationRemoved, VaultEvent},
+    state::{destination_allowlist::DestinationAllowlist, vault_state::VaultState},
+};
+
+pub fn remove_allowed_destination(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    destination: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let authority = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let allowlist_account = next_account_info(accounts_iter)?;
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    vault_state.check_authority(authority, accounts_iter.as_slice())?;
+
+    if allowlist_account.owner != program_id {
+        return Err(VaultError::InvalidAllowlist.into());
+    }
+
+    let mut allowlist = DestinationAllowlist::unpack(&allowlist_account.data.borrow())?;
+
+    allowlist.verify_pda(
+        allowlist_account.key,
+        vault_state_pda.key,
+        vault_state.generation,
+        program_id,
+    )?;
+
+    let index = allowlist
+        .position(&destination)
+        .ok_or(VaultError::AllowlistEntryNotFound)?;
+
+    allowlist.entries.remove(index);
+
+    allowlist_account.resize(allowlist.space())?;
+    allowlist.pack(&mut allowlist_account.data.borrow_mut())?;
+
+    VaultEvent::AllowedDestinationRemoved(AllowedDestinationRemoved {
+        vault_state: *vault_state_pda.key,
+        destination,
+    })
+    .emit()?;
+
+    Ok(())
+}
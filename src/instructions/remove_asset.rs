@@ -0,0 +1,103 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program::invoke_signed,
+    pubkey::Pubkey,
+};
+use spl_token_2022::{
+    extension::StateWithExtensions, instruction::close_account, state::Account as TokenAccount,
+};
+
+use crate::{
+    error::VaultError,
+    events::{AssetRemoved, VaultEvent},
+    instructions::close::close_program_account,
+    state::{vault_asset::VaultAsset, vault_state::VaultState},
+};
+
+pub fn remove_asset(program_id: &Pubkey, accounts: &[AccountInfo], mint: Pubkey) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let authority = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let asset_account = next_account_info(accounts_iter)?;
+    let token_account = next_account_info(accounts_iter)?;
+    let rent_receiver = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    vault_state.check_authority(authority, accounts_iter.as_slice())?;
+
+    if asset_account.owner != program_id {
+        return Err(VaultError::InvalidAsset.into());
+    }
+
+    let asset = VaultAsset::unpack(&asset_account.data.borrow())?;
+
+    asset.verify_pda(asset_account.key, vault_state_pda.key, program_id)?;
+
+    if asset.mint != mint {
+        return Err(VaultError::MintMismatch.into());
+    }
+
+    if token_account.key != &asset.token_account {
+        return Err(VaultError::InvalidVaultAccount.into());
+    }
+
+    if token_program.key != &asset.token_program || token_account.owner != token_program.key {
+        return Err(VaultError::InvalidTokenProgram.into());
+    }
+
+    let token = StateWithExtensions::<TokenAccount>::unpack(&token_account.data.borrow())
+        .map_err(|_| VaultError::InvalidTokenAccount)?
+        .base;
+
+    if token.amount != 0 {
+        return Err(VaultError::VaultNotEmpty.into());
+    }
+
+    let close_ix = close_account(
+        token_program.key,
+        token_account.key,
+        rent_receiver.key,
+        vault_state_pda.key,
+        &[],
+    )?;
+
+    vault_state.with_signer_seeds(|seeds| {
+        invoke_signed(
+            &close_ix,
+            &[
+                token_account.clone(),
+                rent_receiver.clone(),
+                vault_state_pda.clone(),
+                token_program.clone(),
+            ],
+            &[seeds],
+        )
+    })?;
+
+    // close the asset slot, returning its rent
+    close_program_account(asset_account, rent_receiver)?;
+
+    vault_state.asset_count = vault_state
+        .asset_count
+        .checked_sub(1)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    VaultEvent::AssetRemoved(AssetRemoved {
+        vault_state: *vault_state_pda.key,
+        mint,
+    })
+    .emit()?;
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::VaultError,
+    events::{VaultEvent, WithdrawalRequested},
+    instructions::initialize::create_pda_account,
+    state::{
+        account_type::AccountType,
+        pending_withdrawal::{PendingWithdrawal, WithdrawalStatus},
+        vault_state::VaultState,
+    },
+};
+
+pub fn request_withdraw(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+    destination: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let authority = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let pending_withdrawal_account = next_account_info(accounts_iter)?;
+    let payer = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if !payer.is_signer {
+        return Err(VaultError::MissingRequiredSignature.into());
+    }
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    vault_state.check_authority(authority, accounts_iter.as_slice())?;
+
+    if !vault_state.is_timelocked() {
+        return Err(VaultError::NotTimelocked.into());
+    }
+
+    vault_state.check_not_pooled()?;
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(VaultError::InvalidSystemProgram.into());
+    }
+
+    let request_id = vault_state.withdrawal_request_count;
+    let request_id_bytes = request_id.to_le_bytes();
+    let generation_bytes = vault_state.generation.to_le_bytes();
+
+    let (expected_pending_withdrawal_pda, bump) = Pubkey::find_program_address(
+        &[
+            b"withdrawal",
+            vault_state_pda.key.as_ref(),
+            &generation_bytes,
+            &request_id_bytes,
+        ],
+        program_id,
+    );
+
+    if pending_withdrawal_account.key != &expected_pending_withdrawal_pda {
+        return Err(VaultError::InvalidWithdrawalRequest.into());
+    }
+
+    create_pda_account(
+        payer,
+        pending_withdrawal_account,
+        PendingWithdrawal::LEN,
+        program_id,
+        system_program,
+        &[
+            b"withdrawal",
+            vault_state_pda.key.as_ref(),
+            &generation_bytes,
+            &request_id_bytes,
+            &[bump],
+        ],
+    )?;
+
+    let requested_at = Clock::get()?.unix_timestamp;
+
+    let pending_withdrawal = PendingWithdrawal {
+        account_type: AccountType::PendingWithdrawal,
+        vault_state: *vault_state_pda.key,
+        request_id,
+        amount,
+        destination,
+        requested_at,
+        status: WithdrawalStatus::Pending,
+        bump,
+    };
+
+    pending_withdrawal.pack(&mut pending_withdrawal_account.data.borrow_mut())?;
+
+    vault_state.withdrawal_request_count = request_id
+        .checked_add(1)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    VaultEvent::WithdrawalRequested(WithdrawalRequested {
+        vault_state: *vault_state_pda.key,
+        request_id,
+        amount,
+        destination,
+        requested_at,
+    })
+    .emit()?;
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::VaultError,
+    events::{DelegateRevoked, VaultEvent},
+    instructions::close::close_program_account,
+    state::{delegate::Delegate, vault_state::VaultState},
+};
+
+pub fn revoke_delegate(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let authority = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let delegate_account = next_account_info(accounts_iter)?;
+    let rent_receiver = next_account_info(accounts_iter)?;
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    vault_state.check_authority(authority, accounts_iter.as_slice())?;
+
+    if delegate_account.owner != program_id {
+        return Err(VaultError::InvalidDelegate.into());
+    }
+
+    let delegation = Delegate::unpack(&delegate_account.data.borrow())?;
+
+    delegation.verify_pda(
+        delegate_account.key,
+        vault_state_pda.key,
+        vault_state.generation,
+        program_id,
+    )?;
+
+    // close the delegate account, returning its rent
+    close_program_account(delegate_account, rent_receiver)?;
+
+    VaultEvent::DelegateRevoked(DelegateRevoked {
+        vault_state: *vault_state_pda.key,
+        delegate: delegation.delegate,
+    })
+    .emit()?;
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::VaultError,
+    events::{RateLimitUpdated, VaultEvent},
+    state::vault_state::{RateLimit, RateLimitPeriod, VaultState, RATE_LIMIT_CHANGE_DELAY},
+};
+
+pub fn set_rate_limit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    rate_limit: RateLimit,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let authority = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    vault_state.check_authority(authority, accounts_iter.as_slice())?;
+
+    if !rate_limit.is_valid() {
+        return Err(VaultError::InvalidRateLimit.into());
+    }
+
+    let clock = Clock::get()?;
+    vault_state.apply_pending_rate_limit(&clock);
+
+    let effective_at = if vault_state.rate_limit.period == RateLimitPeriod::None {
+        // a first limit only tightens, so it applies at once
+        vault_state.rate_limit = rate_limit;
+        vault_state.pending_rate_limit = RateLimit::default();
+        vault_state.pending_rate_limit_at = 0;
+        vault_state.window_start = 0;
+        vault_state.window_used = 0;
+
+        clock.unix_timestamp
+    } else {
+        vault_state.pending_rate_limit = rate_limit;
+        vault_state.pending_rate_limit_at = clock
+            .unix_timestamp
+            .checked_add(RATE_LIMIT_CHANGE_DELAY)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        vault_state.pending_rate_limit_at
+    };
+
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    VaultEvent::RateLimitUpdated(RateLimitUpdated {
+        vault_state: *vault_state_pda.key,
+        rate_limit,
+        effective_at,
+    })
+    .emit()?;
+
+    Ok(())
+}
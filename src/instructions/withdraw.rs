@@ -1,19 +1,59 @@
-use borsh::BorshDeserialize;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    clock::Clock,
     entrypoint::ProgramResult,
-    msg,
-    program::invoke_signed,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
     program_pack::Pack,
     pubkey::Pubkey,
     system_instruction::transfer,
+    sysvar::Sysvar,
 };
-use spl_token::state::Account as TokenAccount;
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    instruction::{close_account, initialize_account3},
+    onchain::invoke_transfer_checked,
+    state::{Account as TokenAccount, Mint},
+};
+
+use crate::{
+    error::VaultError,
+    events::{AssetWithdrawn, VaultEvent, Withdrawn},
+    instructions::{
+        add_asset::next_asset,
+        initialize::create_pda_account,
+        redeem::{vault_assets, withdrawable_assets},
+    },
+    state::{
+        destination_allowlist::DestinationAllowlist,
+        vault_asset::VaultAsset,
+        vault_state::{RateLimitPeriod, VaultState},
+    },
+};
+
+/// Accounts needed to move funds into or out of a vault.
+pub struct VaultTransferAccounts<'a, 'info> {
+    pub vault_state_pda: &'a AccountInfo<'info>,
+    pub source_token_account: &'a AccountInfo<'info>,
+    pub destination_token_account: &'a AccountInfo<'info>,
+    pub token_mint: Option<&'a AccountInfo<'info>>, // SPL vaults only
+    pub token_program: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    /// Searched by key for a transfer hook's extra accounts.
+    pub transfer_hook_accounts: &'a [AccountInfo<'info>],
+}
 
-use crate::state::vault_state::VaultState;
+pub fn withdraw(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    process_withdraw(program_id, accounts, Some(amount))
+}
 
-pub fn withdraw(_program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+/// Shared by `Withdraw` and `WithdrawAll`; `None` withdraws everything the
+/// source account can pay out.
+pub fn process_withdraw(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: Option<u64>,
+) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
 
     let authority = next_account_info(accounts_iter)?;
@@ -23,27 +63,220 @@ pub fn withdraw(_program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) ->
     let token_program = next_account_info(accounts_iter)?;
     let system_program = next_account_info(accounts_iter)?;
 
-    if !authority.is_signer {
-        return Err(ProgramError::MissingRequiredSignature);
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    let (token_mint, asset) = if vault_state.uses_asset_slot(source_token_account.key) {
+        let (token_mint, asset_account, asset) =
+            next_asset(program_id, vault_state_pda.key, accounts_iter)?;
+        (Some(token_mint), Some((asset_account, asset)))
+    } else if vault_state.is_native {
+        (None, None)
+    } else {
+        (Some(next_account_info(accounts_iter)?), None)
+    };
+
+    // a wrapping vault pays a wallet, rather than a token account, in lamports
+    let unwrap_accounts = if asset.is_none()
+        && vault_state.wrap_native
+        && destination_token_account.owner == &solana_program::system_program::id()
+    {
+        Some((
+            next_account_info(accounts_iter)?,
+            next_account_info(accounts_iter)?,
+        ))
+    } else {
+        None
+    };
+
+    let clock = Clock::get()?;
+
+    if vault_state.allowlist_enabled {
+        check_allowed_destination(
+            program_id,
+            vault_state_pda.key,
+            vault_state.generation,
+            next_account_info(accounts_iter)?,
+            destination_token_account.key,
+            clock.unix_timestamp,
+        )?;
+    }
+
+    vault_state.check_authority(authority, accounts_iter.as_slice())?;
+
+    vault_state.check_not_paused()?;
+
+    if vault_state.is_timelocked() {
+        return Err(VaultError::WithdrawTimelocked.into());
+    }
+
+    // asset slots are not part of the pool, and the rate limit cannot meter
+    // mints other than the vault's own
+    if asset.is_none() {
+        vault_state.check_not_pooled()?;
+    } else {
+        vault_state.apply_pending_rate_limit(&clock);
+
+        if vault_state.rate_limit.period != RateLimitPeriod::None {
+            return Err(VaultError::AssetRateLimited.into());
+        }
+    }
+
+    let withdraws_all = amount.is_none();
+
+    let amount = match (amount, &asset) {
+        (Some(amount), _) => amount,
+        (None, Some(_)) => {
+            StateWithExtensions::<TokenAccount>::unpack(&source_token_account.data.borrow())
+                .map_err(|_| VaultError::InvalidTokenAccount)?
+                .base
+                .amount
+        }
+        (None, None) => withdrawable_assets(&vault_state, source_token_account)?,
+    };
+
+    // an empty vault fails rather than emitting a zero withdrawal
+    if withdraws_all && amount == 0 {
+        return Err(VaultError::InsufficientVaultBalance.into());
     }
 
-    let vault_state = VaultState::try_from_slice(&vault_state_pda.data.borrow())?;
+    let transfer_accounts = VaultTransferAccounts {
+        vault_state_pda,
+        source_token_account,
+        destination_token_account,
+        token_mint,
+        token_program,
+        system_program,
+        transfer_hook_accounts: accounts,
+    };
 
-    if &vault_state.authority != authority.key {
-        return Err(ProgramError::IllegalOwner);
+    // stats are denominated in the vault's own token, so asset withdrawals
+    // only count towards the asset's totals
+    if let Some((asset_account, mut asset)) = asset {
+        asset.total_withdrawn = asset
+            .total_withdrawn
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        asset.pack(&mut asset_account.data.borrow_mut())?;
+
+        return transfer_asset_from_vault(
+            &vault_state,
+            &asset,
+            &transfer_accounts,
+            amount,
+            Some(authority.key),
+        );
+    }
+
+    check_withdrawable(&vault_state, source_token_account, amount)?;
+
+    vault_state.consume_rate_limit(amount, &clock)?;
+    vault_state
+        .stats
+        .record_withdrawal(amount, clock.unix_timestamp)?;
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    if let Some((unwrap_account, payer)) = unwrap_accounts {
+        return unwrap_from_vault(
+            &vault_state,
+            &transfer_accounts,
+            unwrap_account,
+            payer,
+            amount,
+            Some(authority.key),
+        );
+    }
+
+    transfer_from_vault(
+        &vault_state,
+        &transfer_accounts,
+        amount,
+        Some(authority.key),
+    )?;
+
+    Ok(())
+}
+
+/// Fails unless `destination` is on the vault's allowlist and past its
+/// addition delay.
+pub fn check_allowed_destination(
+    program_id: &Pubkey,
+    vault_state: &Pubkey,
+    generation: u64,
+    allowlist_account: &AccountInfo,
+    destination: &Pubkey,
+    now: i64,
+) -> ProgramResult {
+    if allowlist_account.owner != program_id {
+        return Err(VaultError::InvalidAllowlist.into());
+    }
+
+    let allowlist = DestinationAllowlist::unpack(&allowlist_account.data.borrow())?;
+
+    allowlist.verify_pda(allowlist_account.key, vault_state, generation, program_id)?;
+
+    if !allowlist.is_allowed(destination, now) {
+        return Err(VaultError::DestinationNotAllowed.into());
+    }
+
+    Ok(())
+}
+
+/// Fails unless the vault can pay `amount` without touching funds a refundable
+/// vault owes depositors on their receipts.
+pub fn check_withdrawable(
+    vault_state: &VaultState,
+    source_token_account: &AccountInfo,
+    amount: u64,
+) -> ProgramResult {
+    if amount > withdrawable_assets(vault_state, source_token_account)? {
+        return Err(VaultError::InsufficientVaultBalance.into());
     }
 
+    Ok(())
+}
+
+/// Moves `amount` out of the vault's token account (or vault PDA for native
+/// vaults) into the destination, signing as the vault. Callers are expected to
+/// have authorized the transfer already. When `destination_owner` is set, SPL
+/// destinations must be token accounts owned by that key.
+pub fn transfer_from_vault(
+    vault_state: &VaultState,
+    accounts: &VaultTransferAccounts,
+    amount: u64,
+    destination_owner: Option<&Pubkey>,
+) -> ProgramResult {
+    let VaultTransferAccounts {
+        vault_state_pda,
+        source_token_account,
+        destination_token_account,
+        token_mint,
+        token_program,
+        system_program,
+        ..
+    } = *accounts;
+
     if source_token_account.key != &vault_state.token_account {
-        return Err(ProgramError::InvalidAccountData);
+        return Err(VaultError::InvalidVaultAccount.into());
     }
 
     if vault_state.is_native {
         if system_program.key != &solana_program::system_program::id() {
-            return Err(ProgramError::IncorrectProgramId);
+            return Err(VaultError::InvalidSystemProgram.into());
         }
 
         if source_token_account.owner != system_program.key {
-            return Err(ProgramError::InvalidAccountOwner);
+            return Err(VaultError::InvalidVaultAccount.into());
+        }
+
+        // the vault PDA keeps its rent-exempt minimum until the vault is closed
+        if amount > vault_assets(vault_state, source_token_account)? {
+            return Err(VaultError::InsufficientVaultBalance.into());
         }
 
         invoke_signed(
@@ -64,60 +297,276 @@ pub fn withdraw(_program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) ->
             ]],
         )?
     } else {
-        if token_program.key != &spl_token::id() {
-            return Err(ProgramError::IncorrectProgramId);
-        }
+        let token_mint = token_mint.ok_or(ProgramError::NotEnoughAccountKeys)?;
 
-        if source_token_account.owner != token_program.key {
-            return Err(ProgramError::InvalidAccountOwner);
+        if token_mint.key != &vault_state.token_mint {
+            return Err(VaultError::MintMismatch.into());
         }
 
-        if destination_token_account.owner != token_program.key {
-            return Err(ProgramError::InvalidAccountOwner);
-        }
+        vault_state.check_token_program(token_program.key)?;
 
-        let source_token = TokenAccount::unpack(&source_token_account.data.borrow())?;
+        transfer_tokens(vault_state, accounts, token_mint, amount, destination_owner)?;
+    }
 
-        let destination_token = TokenAccount::unpack(&destination_token_account.data.borrow())?;
+    VaultEvent::Withdrawn(Withdrawn {
+        vault_state: *vault_state_pda.key,
+        destination: *destination_token_account.key,
+        amount,
+    })
+    .emit()?;
 
-        if &source_token.owner != vault_state_pda.key {
-            return Err(ProgramError::InvalidAccountOwner);
-        }
+    Ok(())
+}
 
-        if &destination_token.owner != authority.key {
-            return Err(ProgramError::InvalidAccountOwner);
-        }
+/// Pays `amount` out of a wrapping vault as lamports. The wSOL goes to a
+/// temporary token account at `[b"unwrap", vault_state]`, which is closed into
+/// the state PDA straight away; the state PDA then forwards `amount` to the
+/// destination wallet and the temporary account's rent back to `payer`. When
+/// `destination_owner` is set, the destination must be that wallet.
+pub fn unwrap_from_vault<'info>(
+    vault_state: &VaultState,
+    accounts: &VaultTransferAccounts<'_, 'info>,
+    unwrap_account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    amount: u64,
+    destination_owner: Option<&Pubkey>,
+) -> ProgramResult {
+    let VaultTransferAccounts {
+        vault_state_pda,
+        source_token_account,
+        destination_token_account,
+        token_mint,
+        token_program,
+        system_program,
+        ..
+    } = *accounts;
+
+    if source_token_account.key != &vault_state.token_account {
+        return Err(VaultError::InvalidVaultAccount.into());
+    }
+
+    let token_mint = token_mint.ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    if token_mint.key != &vault_state.token_mint {
+        return Err(VaultError::MintMismatch.into());
+    }
+
+    vault_state.check_token_program(token_program.key)?;
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(VaultError::InvalidSystemProgram.into());
+    }
 
-        if source_token.mint != destination_token.mint {
-            return Err(ProgramError::InvalidAccountData);
+    if let Some(destination_owner) = destination_owner {
+        if destination_token_account.key != destination_owner {
+            return Err(VaultError::InvalidTokenAccountOwner.into());
         }
+    }
+
+    if !payer.is_signer {
+        return Err(VaultError::MissingRequiredSignature.into());
+    }
+
+    // callers have checked the state account is owned by this program
+    let (expected_unwrap_pda, unwrap_bump) = Pubkey::find_program_address(
+        &[b"unwrap", vault_state_pda.key.as_ref()],
+        vault_state_pda.owner,
+    );
 
-        let transfer_ix = spl_token::instruction::transfer(
+    if unwrap_account.key != &expected_unwrap_pda
+        || unwrap_account.owner != system_program.key
+        || !unwrap_account.data_is_empty()
+    {
+        return Err(VaultError::InvalidUnwrapAccount.into());
+    }
+
+    let unwrap_seeds: &[&[u8]] = &[b"unwrap", vault_state_pda.key.as_ref(), &[unwrap_bump]];
+
+    create_pda_account(
+        payer,
+        unwrap_account,
+        TokenAccount::LEN,
+        token_program.key,
+        system_program,
+        unwrap_seeds,
+    )?;
+
+    invoke(
+        &initialize_account3(
             token_program.key,
-            source_token_account.key,
-            destination_token_account.key,
+            unwrap_account.key,
+            token_mint.key,
             vault_state_pda.key,
-            &[],
-            amount,
-        )?;
+        )?,
+        &[
+            unwrap_account.clone(),
+            token_mint.clone(),
+            token_program.clone(),
+        ],
+    )?;
+
+    transfer_tokens(
+        vault_state,
+        &VaultTransferAccounts {
+            destination_token_account: unwrap_account,
+            ..*accounts
+        },
+        token_mint,
+        amount,
+        Some(vault_state_pda.key),
+    )?;
 
+    let unwrapped = unwrap_account.lamports();
+
+    let close_ix = close_account(
+        token_program.key,
+        unwrap_account.key,
+        vault_state_pda.key,
+        vault_state_pda.key,
+        &[],
+    )?;
+
+    vault_state.with_signer_seeds(|seeds| {
         invoke_signed(
-            &transfer_ix,
+            &close_ix,
             &[
-                source_token_account.clone(),
-                destination_token_account.clone(),
+                unwrap_account.clone(),
+                vault_state_pda.clone(),
                 vault_state_pda.clone(),
                 token_program.clone(),
             ],
-            &[&[
-                b"vault",
-                vault_state_pda.key.as_ref(),
-                &[vault_state.vault_bump],
-            ]],
-        )?;
+            &[seeds],
+        )
+    })?;
+
+    // the state PDA now holds the unwrapped lamports on top of its own rent
+    **vault_state_pda.try_borrow_mut_lamports()? = vault_state_pda
+        .lamports()
+        .checked_sub(unwrapped)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    **destination_token_account.try_borrow_mut_lamports()? = destination_token_account
+        .lamports()
+        .checked_add(amount)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    **payer.try_borrow_mut_lamports()? = payer
+        .lamports()
+        .checked_add(
+            unwrapped
+                .checked_sub(amount)
+                .ok_or(VaultError::ArithmeticOverflow)?,
+        )
+        .ok_or(VaultError::ArithmeticOverflow)?;
+
+    VaultEvent::Withdrawn(Withdrawn {
+        vault_state: *vault_state_pda.key,
+        destination: *destination_token_account.key,
+        amount,
+    })
+    .emit()
+}
+
+/// Moves `amount` out of one of the vault's asset slots, like
+/// `transfer_from_vault` does for the vault's own token.
+pub fn transfer_asset_from_vault(
+    vault_state: &VaultState,
+    asset: &VaultAsset,
+    accounts: &VaultTransferAccounts,
+    amount: u64,
+    destination_owner: Option<&Pubkey>,
+) -> ProgramResult {
+    if accounts.source_token_account.key != &asset.token_account {
+        return Err(VaultError::InvalidVaultAccount.into());
     }
 
-    msg!("Withdrawn {} tokens", amount);
+    let token_mint = accounts
+        .token_mint
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
 
-    Ok(())
+    if token_mint.key != &asset.mint {
+        return Err(VaultError::MintMismatch.into());
+    }
+
+    if accounts.token_program.key != &asset.token_program {
+        return Err(VaultError::InvalidTokenProgram.into());
+    }
+
+    transfer_tokens(vault_state, accounts, token_mint, amount, destination_owner)?;
+
+    VaultEvent::AssetWithdrawn(AssetWithdrawn {
+        vault_state: *accounts.vault_state_pda.key,
+        mint: asset.mint,
+        destination: *accounts.destination_token_account.key,
+        amount,
+    })
+    .emit()
+}
+
+/// Checks both token accounts and moves `amount` between them with
+/// `transfer_checked`, signing as the state PDA.
+fn transfer_tokens<'info>(
+    vault_state: &VaultState,
+    accounts: &VaultTransferAccounts<'_, 'info>,
+    token_mint: &AccountInfo<'info>,
+    amount: u64,
+    destination_owner: Option<&Pubkey>,
+) -> ProgramResult {
+    let VaultTransferAccounts {
+        vault_state_pda,
+        source_token_account,
+        destination_token_account,
+        token_program,
+        transfer_hook_accounts,
+        ..
+    } = *accounts;
+
+    if source_token_account.owner != token_program.key {
+        return Err(VaultError::InvalidTokenAccount.into());
+    }
+
+    if destination_token_account.owner != token_program.key {
+        return Err(VaultError::InvalidTokenAccount.into());
+    }
+
+    let source_token =
+        StateWithExtensions::<TokenAccount>::unpack(&source_token_account.data.borrow())
+            .map_err(|_| VaultError::InvalidTokenAccount)?
+            .base;
+
+    let destination_token =
+        StateWithExtensions::<TokenAccount>::unpack(&destination_token_account.data.borrow())
+            .map_err(|_| VaultError::InvalidTokenAccount)?
+            .base;
+
+    if &source_token.owner != vault_state_pda.key {
+        return Err(VaultError::InvalidTokenAccountOwner.into());
+    }
+
+    if let Some(destination_owner) = destination_owner {
+        if &destination_token.owner != destination_owner {
+            return Err(VaultError::InvalidTokenAccountOwner.into());
+        }
+    }
+
+    if source_token.mint != destination_token.mint {
+        return Err(VaultError::MintMismatch.into());
+    }
+
+    let decimals = StateWithExtensions::<Mint>::unpack(&token_mint.data.borrow())
+        .map_err(|_| VaultError::MintMismatch)?
+        .base
+        .decimals;
+
+    vault_state.with_signer_seeds(|seeds| {
+        invoke_transfer_checked(
+            token_program.key,
+            source_token_account.clone(),
+            token_mint.clone(),
+            destination_token_account.clone(),
+            vault_state_pda.clone(),
+            transfer_hook_accounts,
+            amount,
+            decimals,
+            &[seeds],
+        )
+    })
 }
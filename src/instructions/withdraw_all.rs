@@ -0,0 +1,7 @@
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+use crate::instructions::withdraw::process_withdraw;
+
+pub fn withdraw_all(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    process_withdraw(program_id, accounts, None)
+}
@@ -0,0 +1,110 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::VaultError,
+    events::{Refunded, VaultEvent},
+    instructions::withdraw::{transfer_from_vault, VaultTransferAccounts},
+    state::{depositor_receipt::DepositorReceipt, vault_state::VaultState},
+};
+
+pub fn withdraw_own(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let depositor = next_account_info(accounts_iter)?;
+    let vault_state_pda = next_account_info(accounts_iter)?;
+    let receipt_account = next_account_info(accounts_iter)?;
+    let source_token_account = next_account_info(accounts_iter)?;
+    let destination_token_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if !depositor.is_signer {
+        return Err(VaultError::MissingRequiredSignature.into());
+    }
+
+    if vault_state_pda.owner != program_id {
+        return Err(VaultError::InvalidStateOwner.into());
+    }
+
+    let mut vault_state = VaultState::unpack(&vault_state_pda.data.borrow())?;
+
+    vault_state.verify_pda(vault_state_pda.key, program_id)?;
+
+    let token_mint = if vault_state.is_native {
+        None
+    } else {
+        Some(next_account_info(accounts_iter)?)
+    };
+
+    vault_state.check_not_paused()?;
+
+    if !vault_state.refundable {
+        return Err(VaultError::NotRefundableVault.into());
+    }
+
+    if receipt_account.owner != program_id {
+        return Err(VaultError::InvalidReceipt.into());
+    }
+
+    let mut receipt = DepositorReceipt::unpack(&receipt_account.data.borrow())?;
+
+    receipt.verify_pda(
+        receipt_account.key,
+        vault_state_pda.key,
+        vault_state.generation,
+        program_id,
+    )?;
+
+    if &receipt.depositor != depositor.key {
+        return Err(VaultError::InvalidReceipt.into());
+    }
+
+    receipt.balance = receipt
+        .balance
+        .checked_sub(amount)
+        .ok_or(VaultError::InsufficientReceiptBalance)?;
+    receipt.pack(&mut receipt_account.data.borrow_mut())?;
+
+    let clock = Clock::get()?;
+
+    vault_state.consume_rate_limit(amount, &clock)?;
+    vault_state
+        .stats
+        .record_withdrawal(amount, clock.unix_timestamp)?;
+    vault_state.outstanding_receipts = vault_state
+        .outstanding_receipts
+        .checked_sub(amount)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    vault_state.pack(&mut vault_state_pda.data.borrow_mut())?;
+
+    transfer_from_vault(
+        &vault_state,
+        &VaultTransferAccounts {
+            vault_state_pda,
+            source_token_account,
+            destination_token_account,
+            token_mint,
+            token_program,
+            system_program,
+            transfer_hook_accounts: accounts,
+        },
+        amount,
+        Some(depositor.key),
+    )?;
+
+    VaultEvent::Refunded(Refunded {
+        vault_state: *vault_state_pda.key,
+        depositor: *depositor.key,
+        amount,
+        remaining_balance: receipt.balance,
+    })
+    .emit()?;
+
+    Ok(())
+}
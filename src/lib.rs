@@ -1,4 +1,6 @@
 pub mod entrypoint;
+pub mod error;
+pub mod events;
 pub mod instructions;
 pub mod processor;
 pub mod state;
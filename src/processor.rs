@@ -1,8 +1,20 @@
 use borsh::BorshDeserialize;
 use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
 
+use crate::error::VaultError;
 use crate::instructions::{
-    close::close, deposit::deposit, initialize::initialize, withdraw::withdraw, VaultInstruction,
+    accept_authority::accept_authority, add_allowed_destination::add_allowed_destination,
+    add_asset::add_asset, approve_delegate::approve_delegate, approve_proposal::approve_proposal,
+    cancel_withdraw::cancel_withdraw, close::close,
+    create_withdrawal_proposal::create_withdrawal_proposal, delegated_withdraw::delegated_withdraw,
+    deposit::deposit, deposit_with_receipt::deposit_with_receipt,
+    execute_proposal::execute_proposal, execute_withdraw::execute_withdraw, initialize::initialize,
+    migrate_state::migrate_state, pause::pause, propose_authority::propose_authority,
+    redeem::redeem, reject_proposal::reject_proposal,
+    remove_allowed_destination::remove_allowed_destination, remove_asset::remove_asset,
+    request_withdraw::request_withdraw, revoke_delegate::revoke_delegate,
+    set_rate_limit::set_rate_limit, unpause::unpause, withdraw::withdraw,
+    withdraw_all::withdraw_all, withdraw_own::withdraw_own, VaultInstruction,
 };
 
 pub struct Processor {}
@@ -13,17 +25,68 @@ impl Processor {
         accounts: &[AccountInfo],
         instruction_data: &[u8],
     ) -> ProgramResult {
-        let instruction = VaultInstruction::try_from_slice(&instruction_data)?;
+        let instruction = VaultInstruction::try_from_slice(instruction_data)
+            .map_err(|_| VaultError::InvalidInstruction)?;
 
         match instruction {
             VaultInstruction::Initialize {
                 vault_bump,
                 state_bump,
                 is_native,
-            } => initialize(program_id, accounts, vault_bump, state_bump, is_native),
+                vault_id,
+                config,
+            } => initialize(
+                program_id, accounts, vault_bump, state_bump, is_native, vault_id, config,
+            ),
             VaultInstruction::Deposit { amount } => deposit(program_id, accounts, amount),
             VaultInstruction::Withdraw { amount } => withdraw(program_id, accounts, amount),
             VaultInstruction::Close => close(program_id, accounts),
+            VaultInstruction::MigrateState => migrate_state(program_id, accounts),
+            VaultInstruction::ProposeAuthority { new_authority } => {
+                propose_authority(program_id, accounts, new_authority)
+            }
+            VaultInstruction::AcceptAuthority => accept_authority(program_id, accounts),
+            VaultInstruction::CreateWithdrawalProposal {
+                amount,
+                destination,
+            } => create_withdrawal_proposal(program_id, accounts, amount, destination),
+            VaultInstruction::ApproveProposal => approve_proposal(program_id, accounts),
+            VaultInstruction::RejectProposal => reject_proposal(program_id, accounts),
+            VaultInstruction::ExecuteProposal => execute_proposal(program_id, accounts),
+            VaultInstruction::RequestWithdraw {
+                amount,
+                destination,
+            } => request_withdraw(program_id, accounts, amount, destination),
+            VaultInstruction::ExecuteWithdraw => execute_withdraw(program_id, accounts),
+            VaultInstruction::CancelWithdraw => cancel_withdraw(program_id, accounts),
+            VaultInstruction::SetRateLimit { rate_limit } => {
+                set_rate_limit(program_id, accounts, rate_limit)
+            }
+            VaultInstruction::Pause => pause(program_id, accounts),
+            VaultInstruction::Unpause => unpause(program_id, accounts),
+            VaultInstruction::AddAllowedDestination { destination } => {
+                add_allowed_destination(program_id, accounts, destination)
+            }
+            VaultInstruction::RemoveAllowedDestination { destination } => {
+                remove_allowed_destination(program_id, accounts, destination)
+            }
+            VaultInstruction::ApproveDelegate {
+                delegate,
+                allowance,
+                expires_at,
+            } => approve_delegate(program_id, accounts, delegate, allowance, expires_at),
+            VaultInstruction::RevokeDelegate => revoke_delegate(program_id, accounts),
+            VaultInstruction::DelegatedWithdraw { amount } => {
+                delegated_withdraw(program_id, accounts, amount)
+            }
+            VaultInstruction::Redeem { shares } => redeem(program_id, accounts, shares),
+            VaultInstruction::WithdrawOwn { amount } => withdraw_own(program_id, accounts, amount),
+            VaultInstruction::AddAsset { mint } => add_asset(program_id, accounts, mint),
+            VaultInstruction::RemoveAsset { mint } => remove_asset(program_id, accounts, mint),
+            VaultInstruction::WithdrawAll => withdraw_all(program_id, accounts),
+            VaultInstruction::DepositWithReceipt { amount } => {
+                deposit_with_receipt(program_id, accounts, amount)
+            }
         }
     }
 }
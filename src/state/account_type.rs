@@ -0,0 +1,17 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Tag stored in the first byte of every account owned by the program.
+///
+/// Variants are serialized by index, so new ones must only be appended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum AccountType {
+    Uninitialized,
+    VaultState,
+    WithdrawalProposal,
+    PendingWithdrawal,
+    DestinationAllowlist,
+    Delegate,
+    DepositorReceipt,
+    VaultAsset,
+    ClosedVault,
+}
@@ -0,0 +1,31 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::program_error::ProgramError;
+
+use crate::{error::VaultError, state::account_type::AccountType};
+
+/// What `Close` leaves at a vault state address. Keeps the closed vault's
+/// generation so a vault reopened there derives fresh receipts, delegates,
+/// allowlist, proposals, withdrawals and share mint.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct ClosedVault {
+    pub account_type: AccountType, // always AccountType::ClosedVault
+    pub generation: u64,           // generation of the closed vault
+}
+
+impl ClosedVault {
+    pub const LEN: usize = 1 + 8;
+
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::LEN || data[0] != AccountType::ClosedVault as u8 {
+            return Err(VaultError::InvalidAccountType.into());
+        }
+
+        Self::try_from_slice(data).map_err(|_| VaultError::InvalidStateData.into())
+    }
+
+    pub fn pack(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        let mut data = data;
+        self.serialize(&mut data)?;
+        Ok(())
+    }
+}
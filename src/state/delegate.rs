@@ -0,0 +1,70 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{error::VaultError, state::account_type::AccountType};
+
+/// A key allowed to withdraw up to `allowance` from a vault without the
+/// authority. Lives at `[b"delegate", vault_state, generation, delegate]`.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct Delegate {
+    pub account_type: AccountType, // always AccountType::Delegate
+    pub vault_state: Pubkey,       // vault the delegate may withdraw from
+    pub delegate: Pubkey,          // key that signs DelegatedWithdraw
+    pub allowance: u64,            // amount still available to withdraw
+    pub expires_at: i64,           // Clock::unix_timestamp the delegation ends, 0 for never
+    pub bump: u8,
+}
+
+impl Delegate {
+    pub const LEN: usize = 1 + 32 + 32 + 8 + 8 + 1;
+
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.first() != Some(&(AccountType::Delegate as u8)) {
+            return Err(VaultError::InvalidAccountType.into());
+        }
+
+        Self::try_from_slice(data).map_err(|_| VaultError::InvalidDelegate.into())
+    }
+
+    pub fn pack(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        let mut data = data;
+        self.serialize(&mut data)?;
+        Ok(())
+    }
+
+    /// Checks that `key` is this delegate's PDA under `vault_state`'s current
+    /// generation.
+    pub fn verify_pda(
+        &self,
+        key: &Pubkey,
+        vault_state: &Pubkey,
+        generation: u64,
+        program_id: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        if &self.vault_state != vault_state {
+            return Err(VaultError::InvalidDelegate.into());
+        }
+
+        let expected = Pubkey::create_program_address(
+            &[
+                b"delegate",
+                vault_state.as_ref(),
+                &generation.to_le_bytes(),
+                self.delegate.as_ref(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| VaultError::InvalidDelegate)?;
+
+        if key != &expected {
+            return Err(VaultError::InvalidDelegate.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at != 0 && now >= self.expires_at
+    }
+}
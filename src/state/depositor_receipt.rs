@@ -0,0 +1,67 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{error::VaultError, state::account_type::AccountType};
+
+/// Running record of one depositor's deposits into a vault.
+/// Lives at `[b"receipt", vault_state, generation, depositor]`.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct DepositorReceipt {
+    pub account_type: AccountType, // always AccountType::DepositorReceipt
+    pub vault_state: Pubkey,       // vault the deposits went into
+    pub depositor: Pubkey,
+    pub total_deposited: u64, // cumulative amount deposited
+    pub balance: u64,         // amount still reclaimable with WithdrawOwn
+    pub last_deposit_slot: u64,
+    pub bump: u8,
+}
+
+impl DepositorReceipt {
+    pub const LEN: usize = 1 + 32 + 32 + 8 + 8 + 8 + 1;
+
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.first() != Some(&(AccountType::DepositorReceipt as u8)) {
+            return Err(VaultError::InvalidAccountType.into());
+        }
+
+        Self::try_from_slice(data).map_err(|_| VaultError::InvalidReceipt.into())
+    }
+
+    pub fn pack(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        let mut data = data;
+        self.serialize(&mut data)?;
+        Ok(())
+    }
+
+    /// Checks that `key` is this receipt's PDA under `vault_state`'s current
+    /// generation.
+    pub fn verify_pda(
+        &self,
+        key: &Pubkey,
+        vault_state: &Pubkey,
+        generation: u64,
+        program_id: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        if &self.vault_state != vault_state {
+            return Err(VaultError::InvalidReceipt.into());
+        }
+
+        let expected = Pubkey::create_program_address(
+            &[
+                b"receipt",
+                vault_state.as_ref(),
+                &generation.to_le_bytes(),
+                self.depositor.as_ref(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| VaultError::InvalidReceipt)?;
+
+        if key != &expected {
+            return Err(VaultError::InvalidReceipt.into());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{error::VaultError, state::account_type::AccountType};
+
+/// Seconds before a newly added destination can receive withdrawals.
+pub const ALLOWLIST_ADDITION_DELAY: i64 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct AllowedDestination {
+    pub destination: Pubkey,
+    pub allowed_at: i64, // Clock::unix_timestamp from which withdrawals may use it
+}
+
+impl AllowedDestination {
+    pub const LEN: usize = 32 + 8;
+}
+
+/// Destinations `Withdraw` may pay out to once the vault's allowlist is
+/// enabled. Lives at `[b"allowlist", vault_state, generation]` and is resized as entries
+/// are added or removed.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct DestinationAllowlist {
+    pub account_type: AccountType, // always AccountType::DestinationAllowlist
+    pub vault_state: Pubkey,       // vault the allowlist belongs to
+    pub bump: u8,
+    pub entries: Vec<AllowedDestination>,
+}
+
+impl DestinationAllowlist {
+    /// Size of an allowlist with no entries.
+    pub const BASE_LEN: usize = 1 + 32 + 1 + 4;
+
+    pub fn space(&self) -> usize {
+        Self::BASE_LEN + self.entries.len() * AllowedDestination::LEN
+    }
+
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.first() != Some(&(AccountType::DestinationAllowlist as u8)) {
+            return Err(VaultError::InvalidAccountType.into());
+        }
+
+        Self::try_from_slice(data).map_err(|_| VaultError::InvalidAllowlist.into())
+    }
+
+    pub fn pack(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        let mut data = data;
+        self.serialize(&mut data)?;
+        Ok(())
+    }
+
+    /// Checks that `key` is the allowlist PDA of `vault_state`'s current
+    /// generation.
+    pub fn verify_pda(
+        &self,
+        key: &Pubkey,
+        vault_state: &Pubkey,
+        generation: u64,
+        program_id: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        if &self.vault_state != vault_state {
+            return Err(VaultError::InvalidAllowlist.into());
+        }
+
+        let expected = Pubkey::create_program_address(
+            &[
+                b"allowlist",
+                vault_state.as_ref(),
+                &generation.to_le_bytes(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| VaultError::InvalidAllowlist)?;
+
+        if key != &expected {
+            return Err(VaultError::InvalidAllowlist.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn position(&self, destination: &Pubkey) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| &entry.destination == destination)
+    }
+
+    /// Whether `destination` is listed and its addition delay has passed.
+    pub fn is_allowed(&self, destination: &Pubkey, now: i64) -> bool {
+        self.position(destination)
+            .is_some_and(|index| now >= self.entries[index].allowed_at)
+    }
+}
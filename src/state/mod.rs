@@ -1 +1,9 @@
+pub mod account_type;
+pub mod closed_vault;
+pub mod delegate;
+pub mod depositor_receipt;
+pub mod destination_allowlist;
+pub mod pending_withdrawal;
+pub mod vault_asset;
 pub mod vault_state;
+pub mod withdrawal_proposal;
@@ -0,0 +1,75 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{error::VaultError, state::account_type::AccountType};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum WithdrawalStatus {
+    Pending,
+    Executed,
+    Cancelled,
+}
+
+/// A withdrawal queued on a timelocked vault.
+/// Lives at `[b"withdrawal", vault_state, generation, request_id]`.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct PendingWithdrawal {
+    pub account_type: AccountType, // always AccountType::PendingWithdrawal
+    pub vault_state: Pubkey,       // vault the withdrawal is taken from
+    pub request_id: u64,           // index taken from VaultState::withdrawal_request_count
+    pub amount: u64,
+    pub destination: Pubkey, // account that receives the funds
+    pub requested_at: i64,   // Clock::unix_timestamp when requested
+    pub status: WithdrawalStatus,
+    pub bump: u8,
+}
+
+impl PendingWithdrawal {
+    pub const LEN: usize = 1 + 32 + 8 + 8 + 32 + 8 + 1 + 1;
+
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.first() != Some(&(AccountType::PendingWithdrawal as u8)) {
+            return Err(VaultError::InvalidAccountType.into());
+        }
+
+        Self::try_from_slice(data).map_err(|_| VaultError::InvalidWithdrawalRequest.into())
+    }
+
+    pub fn pack(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        let mut data = data;
+        self.serialize(&mut data)?;
+        Ok(())
+    }
+
+    /// Checks that `key` is this request's PDA under `vault_state`'s current
+    /// generation.
+    pub fn verify_pda(
+        &self,
+        key: &Pubkey,
+        vault_state: &Pubkey,
+        generation: u64,
+        program_id: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        if &self.vault_state != vault_state {
+            return Err(VaultError::InvalidWithdrawalRequest.into());
+        }
+
+        let expected = Pubkey::create_program_address(
+            &[
+                b"withdrawal",
+                vault_state.as_ref(),
+                &generation.to_le_bytes(),
+                &self.request_id.to_le_bytes(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| VaultError::InvalidWithdrawalRequest)?;
+
+        if key != &expected {
+            return Err(VaultError::InvalidWithdrawalRequest.into());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{error::VaultError, state::account_type::AccountType};
+
+/// An extra mint held by a vault alongside its own token, registered with
+/// `AddAsset`. Lives at `[b"asset", vault_state, mint]`; the tokens sit in the
+/// state PDA's associated token account for `mint`.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct VaultAsset {
+    pub account_type: AccountType, // always AccountType::VaultAsset
+    pub vault_state: Pubkey,       // vault holding the asset
+    pub mint: Pubkey,
+    pub token_account: Pubkey, // state PDA's ATA for `mint`
+    pub token_program: Pubkey, // SPL Token or Token-2022
+    pub decimals: u8,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub bump: u8,
+}
+
+impl VaultAsset {
+    pub const LEN: usize = 1 + 32 + 32 + 32 + 32 + 1 + 8 + 8 + 1;
+
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.first() != Some(&(AccountType::VaultAsset as u8)) {
+            return Err(VaultError::InvalidAccountType.into());
+        }
+
+        Self::try_from_slice(data).map_err(|_| VaultError::InvalidAsset.into())
+    }
+
+    pub fn pack(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        let mut data = data;
+        self.serialize(&mut data)?;
+        Ok(())
+    }
+
+    /// Checks that `key` is this asset's PDA under `vault_state`.
+    pub fn verify_pda(
+        &self,
+        key: &Pubkey,
+        vault_state: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        if &self.vault_state != vault_state {
+            return Err(VaultError::InvalidAsset.into());
+        }
+
+        let expected = Pubkey::create_program_address(
+            &[
+                b"asset",
+                vault_state.as_ref(),
+                self.mint.as_ref(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| VaultError::InvalidAsset)?;
+
+        if key != &expected {
+            return Err(VaultError::InvalidAsset.into());
+        }
+
+        Ok(())
+    }
+}
@@ -1,18 +1,509 @@
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_program::pubkey::Pubkey;
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult,
+    program_error::ProgramError, pubkey::Pubkey,
+};
+
+use crate::{error::VaultError, state::account_type::AccountType};
+
+/// Bytes left unused at the end of the account so fields can be added later
+/// without a realloc. New fields take their size out of this.
+pub const VAULT_STATE_RESERVED: usize = 344;
+
+/// Largest multisig signer set a vault can store. Proposal votes are kept as
+/// `u8` bitmasks over the set, so this must not exceed 8.
+pub const MAX_SIGNERS: usize = 8;
+
+/// Seconds a change to an existing rate limit waits before it takes effect.
+pub const RATE_LIMIT_CHANGE_DELAY: i64 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum RateLimitPeriod {
+    #[default]
+    None,
+    Seconds,
+    Epoch,
+}
+
+/// Caps how much can leave the vault per window: `window_seconds` long for
+/// `Seconds`, one epoch for `Epoch`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct RateLimit {
+    pub period: RateLimitPeriod,
+    pub max_amount: u64,
+    pub window_seconds: i64,
+}
+
+impl RateLimit {
+    pub const LEN: usize = 1 + 8 + 8;
+
+    pub fn is_valid(&self) -> bool {
+        match self.period {
+            RateLimitPeriod::None => true,
+            RateLimitPeriod::Seconds => self.max_amount > 0 && self.window_seconds > 0,
+            RateLimitPeriod::Epoch => self.max_amount > 0,
+        }
+    }
+}
+
+/// Running totals kept for clients; vaults migrated from older layouts start
+/// counting at migration and have `created_at` 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct VaultStats {
+    pub total_deposited: u64,
+    pub total_withdrawn: u64, // every outflow, including redemptions and refunds
+    pub deposit_count: u64,
+    pub withdraw_count: u64,
+    pub created_at: i64,       // Clock::unix_timestamp at initialize
+    pub last_activity_ts: i64, // Clock::unix_timestamp of the last deposit or withdrawal
+}
+
+impl VaultStats {
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 8 + 8;
+
+    pub fn record_deposit(&mut self, amount: u64, now: i64) -> ProgramResult {
+        self.total_deposited = self
+            .total_deposited
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        self.deposit_count = self
+            .deposit_count
+            .checked_add(1)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        self.last_activity_ts = now;
+
+        Ok(())
+    }
+
+    pub fn record_withdrawal(&mut self, amount: u64, now: i64) -> ProgramResult {
+        self.total_withdrawn = self
+            .total_withdrawn
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        self.withdraw_count = self
+            .withdraw_count
+            .checked_add(1)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        self.last_activity_ts = now;
+
+        Ok(())
+    }
+}
 
 #[derive(Debug, BorshSerialize, BorshDeserialize)]
 pub struct VaultState {
-    pub authority: Pubkey,     // who controls the vault
-    pub token_mint: Pubkey,    // SPL token mint (or native mint)
-    pub token_account: Pubkey, // asssoicated token account for holding tokens
-    pub state_bump: u8,        // pda bump seed
+    pub account_type: AccountType, // always AccountType::VaultState
+    pub version: u8,               // layout version, see VaultState::VERSION
+    pub authority: Pubkey,         // who controls the vault
+    pub token_mint: Pubkey,        // SPL token mint (or native mint)
+    pub token_account: Pubkey,     // asssoicated token account for holding tokens
+    pub state_bump: u8,            // pda bump seed
     pub vault_bump: u8,
-    pub is_native: bool, // true if SOL vault, false if SPL token vault
+    pub is_native: bool,           // true if SOL vault, false if SPL token vault
+    pub vault_id: u64,             // lets one authority own several vaults
+    pub creator: Pubkey,           // authority at initialize, used in the state PDA seeds
+    pub pending_authority: Pubkey, // proposed new authority, default if none
+    pub signer_count: u8,          // number of used entries in `signers`
+    pub threshold: u8,             // required approvals, 0 for a single-signer vault
+    pub signers: [Pubkey; MAX_SIGNERS],
+    pub proposal_count: u64,           // id of the next withdrawal proposal
+    pub withdraw_delay: i64, // seconds a requested withdrawal waits, 0 for instant withdraws
+    pub guardian: Pubkey,    // may cancel requested withdrawals and pause, default if none
+    pub withdrawal_request_count: u64, // id of the next requested withdrawal
+    pub rate_limit: RateLimit,
+    pub window_start: i64, // timestamp or epoch the current rate limit window began
+    pub window_used: u64,  // amount withdrawn in the current window
+    pub pending_rate_limit: RateLimit, // queued by SetRateLimit
+    pub pending_rate_limit_at: i64, // when the queued limit applies, 0 if none
+    pub paused: bool,      // blocks deposits and withdrawals while set
+    pub allowlist_enabled: bool, // Withdraw only pays out to allowlisted destinations
+    pub share_mint: Pubkey, // pooled vaults only, default otherwise
+    pub refundable: bool,  // depositors may reclaim their receipts with WithdrawOwn
+    pub stats: VaultStats,
+    pub token_program: Pubkey, // SPL Token or Token-2022, default means SPL Token
+    pub decimals: u8,          // token_mint's decimals, 0 if the vault predates recording them
+    pub asset_count: u8,       // extra mints registered with AddAsset
+    pub wrap_native: bool,     // holds wSOL but deposits lamports, see VaultConfig::wrap_native
+    pub legacy_address: bool,  // migrated from the original layout, PDA is `[b"state", creator]`
+    pub outstanding_receipts: u64, // sum of depositor receipt balances, refundable vaults only
+    pub generation: u64, // times the address was reopened, seeds every PDA derived from the vault
+    pub reserved: [u8; VAULT_STATE_RESERVED],
 }
 
 impl VaultState {
+    /// Current layout version written by `initialize`.
+    ///
+    /// 1: account type, version and reserved tail
+    /// 2: `creator` and `pending_authority`
+    pub const VERSION: u8 = 2;
+
+    /// Fixed size of the account, including the reserved tail.
+    pub const LEN: usize = 1
+        + 1
+        + 32
+        + 32
+        + 32
+        + 1
+        + 1
+        + 1
+        + 8
+        + 32
+        + 32
+        + 1
+        + 1
+        + 32 * MAX_SIGNERS
+        + 8
+        + 8
+        + 32
+        + 8
+        + RateLimit::LEN
+        + 8
+        + 8
+        + RateLimit::LEN
+        + 8
+        + 1
+        + 1
+        + 32
+        + 1
+        + VaultStats::LEN
+        + 32
+        + 1
+        + 1
+        + 1
+        + 1
+        + 8
+        + 8
+        + VAULT_STATE_RESERVED;
+
     pub fn space() -> usize {
-        32 + 32 + 32 + 1 + 1 + 1
+        Self::LEN
+    }
+
+    /// Decodes a vault state account, rejecting data that is not tagged as a
+    /// vault state or was written by a newer layout.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(VaultError::InvalidStateData.into());
+        }
+
+        if data[0] != AccountType::VaultState as u8 {
+            return Err(VaultError::InvalidAccountType.into());
+        }
+
+        let state = Self::try_from_slice(data).map_err(|_| VaultError::InvalidStateData)?;
+
+        if state.version != Self::VERSION {
+            return Err(VaultError::UnsupportedStateVersion.into());
+        }
+
+        Ok(state)
+    }
+
+    /// Decodes a vault state account written by any version up to the current
+    /// one and fills in the fields added since. Only used by `migrate_state`.
+    pub fn unpack_outdated(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(VaultError::InvalidStateData.into());
+        }
+
+        if data[0] != AccountType::VaultState as u8 {
+            return Err(VaultError::InvalidAccountType.into());
+        }
+
+        let mut state = Self::try_from_slice(data).map_err(|_| VaultError::InvalidStateData)?;
+
+        if state.version > Self::VERSION {
+            return Err(VaultError::UnsupportedStateVersion.into());
+        }
+
+        if state.version < 2 {
+            state.creator = state.authority;
+            state.pending_authority = Pubkey::default();
+        }
+
+        state.version = Self::VERSION;
+
+        Ok(state)
+    }
+
+    pub fn has_pending_authority(&self) -> bool {
+        self.pending_authority != Pubkey::default()
+    }
+
+    pub fn is_timelocked(&self) -> bool {
+        self.withdraw_delay > 0
+    }
+
+    pub fn is_guardian(&self, key: &Pubkey) -> bool {
+        self.guardian != Pubkey::default() && &self.guardian == key
+    }
+
+    pub fn is_pooled(&self) -> bool {
+        self.share_mint != Pubkey::default()
+    }
+
+    /// Token program that owns the vault's token account and share mint.
+    /// Vaults created before Token-2022 support leave the field zeroed and
+    /// always used SPL Token.
+    pub fn token_program_id(&self) -> Pubkey {
+        if self.token_program == Pubkey::default() {
+            spl_token::id()
+        } else {
+            self.token_program
+        }
+    }
+
+    pub fn check_token_program(&self, token_program: &Pubkey) -> ProgramResult {
+        if token_program != &self.token_program_id() {
+            return Err(VaultError::InvalidTokenProgram.into());
+        }
+
+        Ok(())
+    }
+
+    /// Whether an instruction naming `vault_token_account` targets one of the
+    /// vault's extra assets rather than its own token account.
+    pub fn uses_asset_slot(&self, vault_token_account: &Pubkey) -> bool {
+        self.asset_count > 0 && vault_token_account != &self.token_account
+    }
+
+    /// A pooled vault's own token belongs to its share holders, so only
+    /// `Redeem` may pay it out.
+    pub fn check_not_pooled(&self) -> ProgramResult {
+        if self.is_pooled() {
+            return Err(VaultError::PooledVault.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn check_not_paused(&self) -> ProgramResult {
+        if self.paused {
+            return Err(VaultError::VaultPaused.into());
+        }
+
+        Ok(())
+    }
+
+    /// Swaps in a queued rate limit once its delay has passed.
+    pub fn apply_pending_rate_limit(&mut self, clock: &Clock) {
+        if self.pending_rate_limit_at != 0 && clock.unix_timestamp >= self.pending_rate_limit_at {
+            self.rate_limit = self.pending_rate_limit;
+            self.pending_rate_limit = RateLimit::default();
+            self.pending_rate_limit_at = 0;
+            self.window_start = 0;
+            self.window_used = 0;
+        }
+    }
+
+    /// Counts `amount` against the current rate limit window, starting a new
+    /// window when the previous one has ended.
+    pub fn consume_rate_limit(&mut self, amount: u64, clock: &Clock) -> ProgramResult {
+        self.apply_pending_rate_limit(clock);
+
+        let (now, window_len) = match self.rate_limit.period {
+            RateLimitPeriod::None => return Ok(()),
+            RateLimitPeriod::Seconds => (clock.unix_timestamp, self.rate_limit.window_seconds),
+            RateLimitPeriod::Epoch => (clock.epoch as i64, 1),
+        };
+
+        let window_end = self
+            .window_start
+            .checked_add(window_len)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        if now >= window_end {
+            self.window_start = now;
+            self.window_used = 0;
+        }
+
+        let used = self
+            .window_used
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+
+        if used > self.rate_limit.max_amount {
+            return Err(VaultError::RateLimitExceeded.into());
+        }
+
+        self.window_used = used;
+
+        Ok(())
+    }
+
+    pub fn is_multisig(&self) -> bool {
+        self.threshold > 0
+    }
+
+    /// Position of `key` in the multisig signer set.
+    pub fn signer_index(&self, key: &Pubkey) -> Option<usize> {
+        self.signers[..self.signer_count as usize]
+            .iter()
+            .position(|member| member == key)
+    }
+
+    /// Checks that `authority` is the vault authority and that the action is
+    /// approved: by the authority's own signature for single-signer vaults, or
+    /// by at least `threshold` distinct members of the signer set among
+    /// `signers` for multisig vaults.
+    pub fn check_authority(
+        &self,
+        authority: &AccountInfo,
+        signers: &[AccountInfo],
+    ) -> ProgramResult {
+        if &self.authority != authority.key {
+            return Err(VaultError::Unauthorized.into());
+        }
+
+        if !self.is_multisig() {
+            if !authority.is_signer {
+                return Err(VaultError::MissingRequiredSignature.into());
+            }
+
+            return Ok(());
+        }
+
+        let mut approved = [false; MAX_SIGNERS];
+
+        for signer in signers.iter().filter(|signer| signer.is_signer) {
+            if let Some(index) = self.signer_index(signer.key) {
+                approved[index] = true;
+            }
+        }
+
+        let approvals = approved.iter().filter(|approved| **approved).count();
+
+        if approvals < self.threshold as usize {
+            return Err(VaultError::NotEnoughSigners.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn pack(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        let mut data = data;
+        self.serialize(&mut data)?;
+        Ok(())
+    }
+
+    /// Calls `f` with the state PDA's signer seeds. Vaults migrated from the
+    /// original layout kept their `[b"state", creator]` address.
+    pub fn with_signer_seeds<R>(&self, f: impl FnOnce(&[&[u8]]) -> R) -> R {
+        let vault_id = self.vault_id.to_le_bytes();
+        let bump = [self.state_bump];
+
+        if self.legacy_address {
+            f(&[b"state", self.creator.as_ref(), &bump])
+        } else {
+            f(&[b"state", self.creator.as_ref(), &vault_id, &bump])
+        }
+    }
+
+    /// Re-derives the state PDA from the stored seeds and checks it matches `key`.
+    pub fn verify_pda(&self, key: &Pubkey, program_id: &Pubkey) -> Result<(), ProgramError> {
+        let expected = self
+            .with_signer_seeds(|seeds| Pubkey::create_program_address(seeds, program_id))
+            .map_err(|_| VaultError::InvalidStatePda)?;
+
+        if key != &expected {
+            return Err(VaultError::InvalidStatePda.into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Unversioned layout written before `VaultState` gained its account type tag,
+/// version and reserved tail. Only read by `migrate_state`.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct LegacyVaultState {
+    pub authority: Pubkey,
+    pub token_mint: Pubkey,
+    pub token_account: Pubkey,
+    pub state_bump: u8,
+    pub vault_bump: u8,
+    pub is_native: bool,
+    pub vault_id: u64,
+}
+
+impl LegacyVaultState {
+    pub const LEN: usize = 32 + 32 + 32 + 1 + 1 + 1 + 8;
+
+    pub fn into_current(self) -> VaultState {
+        VaultState {
+            account_type: AccountType::VaultState,
+            version: VaultState::VERSION,
+            authority: self.authority,
+            token_mint: self.token_mint,
+            token_account: self.token_account,
+            state_bump: self.state_bump,
+            vault_bump: self.vault_bump,
+            is_native: self.is_native,
+            vault_id: self.vault_id,
+            creator: self.authority,
+            pending_authority: Pubkey::default(),
+            signer_count: 0,
+            threshold: 0,
+            signers: [Pubkey::default(); MAX_SIGNERS],
+            proposal_count: 0,
+            withdraw_delay: 0,
+            guardian: Pubkey::default(),
+            withdrawal_request_count: 0,
+            rate_limit: RateLimit::default(),
+            window_start: 0,
+            window_used: 0,
+            pending_rate_limit: RateLimit::default(),
+            pending_rate_limit_at: 0,
+            paused: false,
+            allowlist_enabled: false,
+            share_mint: Pubkey::default(),
+            refundable: false,
+            stats: VaultStats::default(),
+            token_program: Pubkey::default(),
+            decimals: if self.is_native {
+                spl_token::native_mint::DECIMALS
+            } else {
+                0
+            },
+            asset_count: 0,
+            wrap_native: false,
+            legacy_address: false,
+            outstanding_receipts: 0,
+            generation: 0,
+            reserved: [0; VAULT_STATE_RESERVED],
+        }
+    }
+}
+
+/// Layout written by the first release, before vault ids: the state PDA was
+/// derived from `[b"state", authority]`. Only read by `migrate_state`.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct OriginalVaultState {
+    pub authority: Pubkey,
+    pub token_mint: Pubkey,
+    pub token_account: Pubkey,
+    pub state_bump: u8,
+    pub vault_bump: u8,
+    pub is_native: bool,
+}
+
+impl OriginalVaultState {
+    pub const LEN: usize = 32 + 32 + 32 + 1 + 1 + 1;
+
+    pub fn into_current(self) -> VaultState {
+        let mut state = LegacyVaultState {
+            authority: self.authority,
+            token_mint: self.token_mint,
+            token_account: self.token_account,
+            state_bump: self.state_bump,
+            vault_bump: self.vault_bump,
+            is_native: self.is_native,
+            vault_id: 0,
+        }
+        .into_current();
+
+        state.legacy_address = true;
+
+        state
     }
 }
@@ -0,0 +1,98 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{error::VaultError, state::account_type::AccountType};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum ProposalStatus {
+    Pending,
+    Executed,
+    Rejected,
+}
+
+/// A withdrawal waiting for approval from a multisig vault's signer set.
+/// Lives at `[b"proposal", vault_state, generation, proposal_id]`.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct WithdrawalProposal {
+    pub account_type: AccountType, // always AccountType::WithdrawalProposal
+    pub vault_state: Pubkey,       // vault the proposal withdraws from
+    pub proposal_id: u64,          // index taken from VaultState::proposal_count
+    pub proposer: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey, // account that receives the funds
+    pub approvals: u8,       // bitmask over VaultState::signers
+    pub rejections: u8,      // bitmask over VaultState::signers
+    pub status: ProposalStatus,
+    pub bump: u8,
+}
+
+impl WithdrawalProposal {
+    pub const LEN: usize = 1 + 32 + 8 + 32 + 8 + 32 + 1 + 1 + 1 + 1;
+
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.first() != Some(&(AccountType::WithdrawalProposal as u8)) {
+            return Err(VaultError::InvalidAccountType.into());
+        }
+
+        Self::try_from_slice(data).map_err(|_| VaultError::InvalidProposal.into())
+    }
+
+    pub fn pack(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        let mut data = data;
+        self.serialize(&mut data)?;
+        Ok(())
+    }
+
+    /// Checks that `key` is this proposal's PDA under `vault_state`'s current
+    /// generation.
+    pub fn verify_pda(
+        &self,
+        key: &Pubkey,
+        vault_state: &Pubkey,
+        generation: u64,
+        program_id: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        if &self.vault_state != vault_state {
+            return Err(VaultError::InvalidProposal.into());
+        }
+
+        let expected = Pubkey::create_program_address(
+            &[
+                b"proposal",
+                vault_state.as_ref(),
+                &generation.to_le_bytes(),
+                &self.proposal_id.to_le_bytes(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| VaultError::InvalidProposal)?;
+
+        if key != &expected {
+            return Err(VaultError::InvalidProposal.into());
+        }
+
+        Ok(())
+    }
+
+    /// Records a vote from the signer at `index`, replacing any earlier vote.
+    pub fn record_vote(&mut self, index: usize, approve: bool) {
+        let bit = 1u8 << index;
+
+        if approve {
+            self.approvals |= bit;
+            self.rejections &= !bit;
+        } else {
+            self.rejections |= bit;
+            self.approvals &= !bit;
+        }
+    }
+
+    pub fn approval_count(&self) -> usize {
+        self.approvals.count_ones() as usize
+    }
+
+    pub fn rejection_count(&self) -> usize {
+        self.rejections.count_ones() as usize
+    }
+}
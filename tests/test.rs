@@ -1,24 +1,486 @@
 use std::str::FromStr;
 
+use base64::{engine::general_purpose::STANDARD, Engine};
 use borsh::BorshDeserialize;
 use litesvm::LiteSVM;
 
-use native_vault::instructions::VaultInstruction;
+use litesvm::types::TransactionResult;
+use native_vault::{
+    error::VaultError,
+    events::{
+        decode_events, Deposited, Paused, VaultEvent, VaultInitialized, Withdrawn, EVENT_PREFIX,
+    },
+    instructions::{
+        redeem::{SHARE_DECIMALS_OFFSET, VIRTUAL_SHARES},
+        VaultConfig, VaultInstruction,
+    },
+    state::{
+        account_type::AccountType,
+        closed_vault::ClosedVault,
+        delegate::Delegate,
+        depositor_receipt::DepositorReceipt,
+        destination_allowlist::{
+            AllowedDestination, DestinationAllowlist, ALLOWLIST_ADDITION_DELAY,
+        },
+        pending_withdrawal::{PendingWithdrawal, WithdrawalStatus},
+        vault_asset::VaultAsset,
+        vault_state::{
+            LegacyVaultState, OriginalVaultState, RateLimit, RateLimitPeriod, VaultState,
+            VaultStats, RATE_LIMIT_CHANGE_DELAY,
+        },
+        withdrawal_proposal::{ProposalStatus, WithdrawalProposal},
+    },
+};
 use solana_program::program_pack::Pack;
 use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    instruction::InstructionError,
     message::{AccountMeta, Instruction},
     native_token::LAMPORTS_PER_SOL,
     pubkey::Pubkey,
     rent::Rent,
     signature::Keypair,
     signer::Signer,
-    sysvar::Sysvar,
-    transaction::Transaction,
+    transaction::{Transaction, TransactionError},
 };
 use solana_system_interface::{instruction::create_account, program};
 use spl_associated_token_account::ID as ASSOCIATED_TOKEN_ACCOUNT_PROGRAM_ID;
 use spl_token::{state::Mint, ID as TOKEN_PROGRAM_ID};
-use spl_token_interface::instruction::initialize_mint;
+use spl_token_2022::{
+    extension::{
+        transfer_fee::instruction::initialize_transfer_fee_config, ExtensionType,
+        StateWithExtensions,
+    },
+    instruction::{initialize_mint2, initialize_permanent_delegate},
+    state::{Account as TokenAccount, Mint as Token2022Mint},
+    ID as TOKEN_2022_PROGRAM_ID,
+};
+use spl_token_interface::instruction::{initialize_mint, mint_to};
+
+fn setup() -> (LiteSVM, Pubkey, Keypair) {
+    let mut svm = LiteSVM::new();
+
+    let authority = Keypair::new();
+    svm.airdrop(&authority.pubkey(), 5 * LAMPORTS_PER_SOL)
+        .expect("Failed to airdrop");
+
+    let program_id = Pubkey::from_str("BwzUsvj7pXh8h2fEWCmawbSaGXjzi4yV1ftnztBJq3Ba").unwrap();
+    let program_bytes = include_bytes!("../../target/deploy/vault_native.so");
+    svm.add_program(program_id, program_bytes)
+        .expect("faield to laod program");
+
+    (svm, program_id, authority)
+}
+
+fn token_program_id() -> Pubkey {
+    Pubkey::try_from_slice(TOKEN_PROGRAM_ID.as_ref()).unwrap()
+}
+
+fn associated_token_program_id() -> Pubkey {
+    Pubkey::try_from_slice(ASSOCIATED_TOKEN_ACCOUNT_PROGRAM_ID.as_ref()).unwrap()
+}
+
+fn state_pda(program_id: &Pubkey, authority: &Pubkey, vault_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"state", authority.as_ref(), &vault_id.to_le_bytes()],
+        program_id,
+    )
+}
+
+fn vault_pda(program_id: &Pubkey, vault_state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"vault", vault_state.as_ref()], program_id)
+}
+
+fn token_2022_program_id() -> Pubkey {
+    Pubkey::try_from_slice(TOKEN_2022_PROGRAM_ID.as_ref()).unwrap()
+}
+
+fn ata(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &associated_token_program_id(),
+    )
+    .0
+}
+
+#[allow(clippy::result_large_err)]
+fn send(
+    svm: &mut LiteSVM,
+    ixs: &[Instruction],
+    payer: &Keypair,
+    signers: &[&Keypair],
+) -> TransactionResult {
+    let tx = Transaction::new_signed_with_payer(
+        ixs,
+        Some(&payer.pubkey()),
+        signers,
+        svm.latest_blockhash(),
+    );
+
+    let result = svm.send_transaction(tx);
+    svm.expire_blockhash();
+    result
+}
+
+fn assert_vault_error(result: TransactionResult, error: VaultError) {
+    let err = result.expect_err("transaction should have failed").err;
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    );
+}
+
+fn initialize_ix(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    token_mint: &Pubkey,
+    token_account: &Pubkey,
+    vault_id: u64,
+    is_native: bool,
+) -> Instruction {
+    initialize_ix_with_config(
+        program_id,
+        authority,
+        token_mint,
+        token_account,
+        vault_id,
+        is_native,
+        VaultConfig::default(),
+    )
+}
+
+fn initialize_ix_with_config(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    token_mint: &Pubkey,
+    token_account: &Pubkey,
+    vault_id: u64,
+    is_native: bool,
+    config: VaultConfig,
+) -> Instruction {
+    let (vault_state_pda, state_bump) = state_pda(program_id, authority, vault_id);
+    let (_, vault_bump) = vault_pda(program_id, &vault_state_pda);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(*token_mint, false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new_readonly(associated_token_program_id(), false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::Initialize {
+            vault_bump,
+            state_bump,
+            is_native,
+            vault_id,
+            config,
+        })
+        .unwrap(),
+    }
+}
+
+/// Builds a deposit/withdraw/close instruction; they share the same account layout.
+fn vault_ix(
+    program_id: &Pubkey,
+    instruction: &VaultInstruction,
+    signer: &Pubkey,
+    vault_state: &Pubkey,
+    source: &Pubkey,
+    destination: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*signer, true),
+            AccountMeta::new(*vault_state, false),
+            AccountMeta::new(*source, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(instruction).unwrap(),
+    }
+}
+
+/// Points a `vault_ix` for an SPL vault at `token_program` and appends the
+/// vault's mint, which SPL transfers need.
+fn with_token_mint(mut ix: Instruction, token_program: &Pubkey, mint: &Pubkey) -> Instruction {
+    ix.accounts[4].pubkey = *token_program;
+    ix.accounts.push(AccountMeta::new_readonly(*mint, false));
+    ix
+}
+
+/// Creates a native vault and returns its (state, vault) PDAs.
+fn create_native_vault(
+    svm: &mut LiteSVM,
+    program_id: &Pubkey,
+    authority: &Keypair,
+    vault_id: u64,
+) -> (Pubkey, Pubkey) {
+    let (vault_state_pda, _) = state_pda(program_id, &authority.pubkey(), vault_id);
+    let (vault_account_pda, _) = vault_pda(program_id, &vault_state_pda);
+
+    let ix = initialize_ix(
+        program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        vault_id,
+        true,
+    );
+    send(svm, &[ix], authority, &[authority]).expect("failed to initialize vault");
+
+    (vault_state_pda, vault_account_pda)
+}
+
+/// Creates a native vault controlled by `members` with the given threshold and
+/// deposits 1 SOL into it.
+fn create_multisig_native_vault(
+    svm: &mut LiteSVM,
+    program_id: &Pubkey,
+    authority: &Keypair,
+    members: &[Keypair],
+    threshold: u8,
+) -> (Pubkey, Pubkey) {
+    let (vault_state_pda, _) = state_pda(program_id, &authority.pubkey(), 0);
+    let (vault_account_pda, _) = vault_pda(program_id, &vault_state_pda);
+
+    let config = VaultConfig {
+        signers: members
+            .iter()
+            .map(|member| {
+                solana_program::pubkey::Pubkey::new_from_array(member.pubkey().to_bytes())
+            })
+            .collect(),
+        threshold,
+        ..VaultConfig::default()
+    };
+
+    let initialize = initialize_ix_with_config(
+        program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        0,
+        true,
+        config,
+    );
+    let deposit = vault_ix(
+        program_id,
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    send(svm, &[initialize, deposit], authority, &[authority])
+        .expect("failed to create multisig vault");
+
+    for member in members {
+        svm.airdrop(&member.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    }
+
+    (vault_state_pda, vault_account_pda)
+}
+
+fn create_mint(svm: &mut LiteSVM, mint_authority: &Keypair, token_program: &Pubkey) -> Pubkey {
+    let token_mint = Keypair::new();
+
+    let create_mint_ix = create_account(
+        &mint_authority.pubkey(),
+        &token_mint.pubkey(),
+        Rent::default().minimum_balance(Mint::LEN),
+        Mint::LEN as u64,
+        token_program,
+    );
+
+    // Token-2022 understands the SPL Token encoding, but the builder rejects
+    // any other program id
+    let mut init_mint_ix = initialize_mint(
+        &token_program_id(),
+        &token_mint.pubkey(),
+        &mint_authority.pubkey(),
+        None,
+        9,
+    )
+    .unwrap();
+    init_mint_ix.program_id = *token_program;
+
+    send(
+        svm,
+        &[create_mint_ix, init_mint_ix],
+        mint_authority,
+        &[mint_authority, &token_mint],
+    )
+    .expect("failed to create mint");
+
+    token_mint.pubkey()
+}
+
+/// Builds an idempotent create instruction for `owner`'s associated token account.
+fn create_ata_ix(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: associated_token_program_id(),
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(ata(owner, mint, token_program), false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new_readonly(*token_program, false),
+        ],
+        data: vec![1],
+    }
+}
+
+/// Creates `owner`'s associated token account and mints `amount` into it.
+fn fund_token_account(
+    svm: &mut LiteSVM,
+    mint: &Pubkey,
+    mint_authority: &Keypair,
+    owner: &Pubkey,
+    amount: u64,
+    token_program: &Pubkey,
+) -> Pubkey {
+    let token_account = ata(owner, mint, token_program);
+
+    let create_ata_ix = create_ata_ix(&mint_authority.pubkey(), owner, mint, token_program);
+
+    let mut mint_to_ix = mint_to(
+        &token_program_id(),
+        mint,
+        &token_account,
+        &mint_authority.pubkey(),
+        &[],
+        amount,
+    )
+    .unwrap();
+    mint_to_ix.program_id = *token_program;
+
+    send(
+        svm,
+        &[create_ata_ix, mint_to_ix],
+        mint_authority,
+        &[mint_authority],
+    )
+    .expect("failed to fund token account");
+
+    token_account
+}
+
+/// Converts an instruction built against the program-side SDK, such as the
+/// Token-2022 builders, into one litesvm accepts.
+fn to_sdk_ix(ix: solana_program::instruction::Instruction) -> Instruction {
+    Instruction {
+        program_id: Pubkey::new_from_array(ix.program_id.to_bytes()),
+        accounts: ix
+            .accounts
+            .into_iter()
+            .map(|meta| AccountMeta {
+                pubkey: Pubkey::new_from_array(meta.pubkey.to_bytes()),
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+            .collect(),
+        data: ix.data,
+    }
+}
+
+/// Creates a Token-2022 mint carrying `extensions`. `init_extensions` returns
+/// the instructions that initialize them for the given mint address.
+fn create_mint_with_extensions(
+    svm: &mut LiteSVM,
+    mint_authority: &Keypair,
+    extensions: &[ExtensionType],
+    init_extensions: impl Fn(
+        &solana_program::pubkey::Pubkey,
+    ) -> Vec<solana_program::instruction::Instruction>,
+) -> Pubkey {
+    let token_mint = Keypair::new();
+    let mint_key = solana_program::pubkey::Pubkey::new_from_array(token_mint.pubkey().to_bytes());
+    let space = ExtensionType::try_calculate_account_len::<Token2022Mint>(extensions).unwrap();
+
+    let mut ixs = vec![create_account(
+        &mint_authority.pubkey(),
+        &token_mint.pubkey(),
+        Rent::default().minimum_balance(space),
+        space as u64,
+        &token_2022_program_id(),
+    )];
+    ixs.extend(init_extensions(&mint_key).into_iter().map(to_sdk_ix));
+    ixs.push(to_sdk_ix(
+        initialize_mint2(
+            &spl_token_2022::id(),
+            &mint_key,
+            &solana_program::pubkey::Pubkey::new_from_array(mint_authority.pubkey().to_bytes()),
+            None,
+            9,
+        )
+        .unwrap(),
+    ));
+
+    send(svm, &ixs, mint_authority, &[mint_authority, &token_mint]).expect("failed to create mint");
+
+    token_mint.pubkey()
+}
+
+/// Balance of a token account owned by either token program.
+fn token_balance(svm: &LiteSVM, token_account: &Pubkey) -> u64 {
+    let account = svm.get_account(token_account).unwrap();
+    StateWithExtensions::<TokenAccount>::unpack(&account.data)
+        .unwrap()
+        .base
+        .amount
+}
+
+/// Creates an SPL vault for a fresh mint of `token_program` and returns
+/// (state, vault ATA, mint, mint authority).
+fn create_spl_vault(
+    svm: &mut LiteSVM,
+    program_id: &Pubkey,
+    authority: &Keypair,
+    vault_id: u64,
+    token_program: &Pubkey,
+) -> (Pubkey, Pubkey, Pubkey, Keypair) {
+    let mint_authority = Keypair::new();
+    svm.airdrop(&mint_authority.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop mint auth");
+
+    let token_mint = create_mint(svm, &mint_authority, token_program);
+
+    let (vault_state_pda, _) = state_pda(program_id, &authority.pubkey(), vault_id);
+    let vault_token_account = ata(&vault_state_pda, &token_mint, token_program);
+
+    let mut ix = initialize_ix(
+        program_id,
+        &authority.pubkey(),
+        &token_mint,
+        &vault_token_account,
+        vault_id,
+        false,
+    );
+    ix.accounts[4].pubkey = *token_program;
+    send(svm, &[ix], authority, &[authority]).expect("failed to initialize vault");
+
+    (
+        vault_state_pda,
+        vault_token_account,
+        token_mint,
+        mint_authority,
+    )
+}
 
 #[test]
 pub fn test_vault_sol() {
@@ -37,8 +499,16 @@ pub fn test_vault_sol() {
     let associated_token_program_id: Pubkey =
         Pubkey::try_from_slice(ASSOCIATED_TOKEN_ACCOUNT_PROGRAM_ID.as_ref()).unwrap();
 
-    let (vault_state_pda, state_bump) =
-        Pubkey::find_program_address(&[b"state", authority.pubkey().as_ref()], &program_id);
+    let vault_id: u64 = 0;
+
+    let (vault_state_pda, state_bump) = Pubkey::find_program_address(
+        &[
+            b"state",
+            authority.pubkey().as_ref(),
+            &vault_id.to_le_bytes(),
+        ],
+        &program_id,
+    );
 
     let (vault_account_pda, vault_bump) =
         Pubkey::find_program_address(&[b"vault", vault_state_pda.as_ref()], &program_id);
@@ -50,6 +520,8 @@ pub fn test_vault_sol() {
         vault_bump,
         state_bump,
         is_native: true,
+        vault_id,
+        config: VaultConfig::default(),
     })
     .expect("Failed to serialize");
 
@@ -85,7 +557,7 @@ pub fn test_vault_sol() {
 
     // 2. deposit in the vault
     let ix_data = borsh::to_vec(&VaultInstruction::Deposit {
-        amount: 1 * LAMPORTS_PER_SOL,
+        amount: LAMPORTS_PER_SOL,
     })
     .expect("Failed to serialize");
 
@@ -120,7 +592,7 @@ pub fn test_vault_sol() {
 
     // 3. withdraw from the vault
     let ix_data = borsh::to_vec(&VaultInstruction::Withdraw {
-        amount: 1 * LAMPORTS_PER_SOL,
+        amount: LAMPORTS_PER_SOL,
     })
     .expect("Failed to serialize");
 
@@ -210,8 +682,16 @@ pub fn test_vault_spl() {
     let associated_token_program_id: Pubkey =
         Pubkey::try_from_slice(ASSOCIATED_TOKEN_ACCOUNT_PROGRAM_ID.as_ref()).unwrap();
 
-    let (vault_state_pda, state_bump) =
-        Pubkey::find_program_address(&[b"state", authority.pubkey().as_ref()], &program_id);
+    let vault_id: u64 = 0;
+
+    let (vault_state_pda, state_bump) = Pubkey::find_program_address(
+        &[
+            b"state",
+            authority.pubkey().as_ref(),
+            &vault_id.to_le_bytes(),
+        ],
+        &program_id,
+    );
 
     let token_mint = Keypair::new();
     let mint_authority = Keypair::new();
@@ -232,8 +712,18 @@ pub fn test_vault_spl() {
         &mint_authority.pubkey(),
         None,
         9,
+    )
+    .unwrap();
+
+    let tx = Transaction::new_signed_with_payer(
+        &[create_mint_ix, init_mint_ix],
+        Some(&mint_authority.pubkey()),
+        &[&mint_authority, &token_mint],
+        svm.latest_blockhash(),
     );
 
+    svm.send_transaction(tx).expect("failed to create mint");
+
     let (vault_token_account, vault_bump) = Pubkey::find_program_address(
         &[
             &vault_state_pda.to_bytes(),
@@ -252,11 +742,46 @@ pub fn test_vault_spl() {
         &associated_token_program_id,
     );
 
+    // fund the authority with tokens to deposit
+    let create_ata_ix = Instruction {
+        program_id: associated_token_program_id,
+        accounts: vec![
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new(authority_token_account, false),
+            AccountMeta::new_readonly(authority.pubkey(), false),
+            AccountMeta::new_readonly(token_mint.pubkey(), false),
+            AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new_readonly(token_program_id, false),
+        ],
+        data: vec![0],
+    };
+
+    let mint_to_ix = mint_to(
+        &token_program_id,
+        &token_mint.pubkey(),
+        &authority_token_account,
+        &mint_authority.pubkey(),
+        &[],
+        LAMPORTS_PER_SOL,
+    )
+    .unwrap();
+
+    let tx = Transaction::new_signed_with_payer(
+        &[create_ata_ix, mint_to_ix],
+        Some(&authority.pubkey()),
+        &[&authority, &mint_authority],
+        svm.latest_blockhash(),
+    );
+
+    svm.send_transaction(tx).expect("failed to fund authority");
+
     // 1. initialise the vault
     let ix_data = borsh::to_vec(&VaultInstruction::Initialize {
         vault_bump,
         state_bump,
         is_native: false,
+        vault_id,
+        config: VaultConfig::default(),
     })
     .expect("Failed to serialize");
 
@@ -301,7 +826,7 @@ pub fn test_vault_spl() {
 
     // 2. deposit in the vault
     let ix_data = borsh::to_vec(&VaultInstruction::Deposit {
-        amount: 1 * LAMPORTS_PER_SOL,
+        amount: LAMPORTS_PER_SOL,
     })
     .expect("Failed to serialize");
 
@@ -314,6 +839,7 @@ pub fn test_vault_spl() {
             AccountMeta::new(vault_token_account, false),
             AccountMeta::new_readonly(token_program_id, false),
             AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new_readonly(token_mint.pubkey(), false),
         ],
         data: ix_data,
     };
@@ -336,7 +862,7 @@ pub fn test_vault_spl() {
 
     // 3. withdraw from the vault
     let ix_data = borsh::to_vec(&VaultInstruction::Withdraw {
-        amount: 1 * LAMPORTS_PER_SOL,
+        amount: LAMPORTS_PER_SOL,
     })
     .expect("Failed to serialize");
 
@@ -349,6 +875,7 @@ pub fn test_vault_spl() {
             AccountMeta::new(authority_token_account, false),
             AccountMeta::new_readonly(token_program_id, false),
             AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new_readonly(token_mint.pubkey(), false),
         ],
         data: ix_data,
     };
@@ -381,6 +908,7 @@ pub fn test_vault_spl() {
             AccountMeta::new(authority_token_account, false),
             AccountMeta::new_readonly(token_program_id, false),
             AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new_readonly(token_mint.pubkey(), false),
         ],
         data: ix_data,
     };
@@ -408,3 +936,4476 @@ pub fn test_vault_spl() {
     let logs = result.unwrap().pretty_logs();
     println!("{}", logs);
 }
+
+#[test]
+pub fn test_multiple_vaults_per_authority() {
+    let mut svm = LiteSVM::new();
+
+    let authority = Keypair::new();
+    svm.airdrop(&authority.pubkey(), 5 * LAMPORTS_PER_SOL)
+        .expect("Failed to airdrop");
+
+    let program_id = Pubkey::from_str("BwzUsvj7pXh8h2fEWCmawbSaGXjzi4yV1ftnztBJq3Ba").unwrap();
+    let program_bytes = include_bytes!("../../target/deploy/vault_native.so");
+    svm.add_program(program_id, program_bytes)
+        .expect("faield to laod program");
+
+    let token_program_id = Pubkey::try_from_slice(TOKEN_PROGRAM_ID.as_ref()).unwrap();
+    let associated_token_program_id: Pubkey =
+        Pubkey::try_from_slice(ASSOCIATED_TOKEN_ACCOUNT_PROGRAM_ID.as_ref()).unwrap();
+
+    let mut vault_state_pdas = vec![];
+
+    for vault_id in [0u64, 1u64] {
+        let (vault_state_pda, state_bump) = Pubkey::find_program_address(
+            &[
+                b"state",
+                authority.pubkey().as_ref(),
+                &vault_id.to_le_bytes(),
+            ],
+            &program_id,
+        );
+
+        let (vault_account_pda, vault_bump) =
+            Pubkey::find_program_address(&[b"vault", vault_state_pda.as_ref()], &program_id);
+
+        let ix_data = borsh::to_vec(&VaultInstruction::Initialize {
+            vault_bump,
+            state_bump,
+            is_native: true,
+            vault_id,
+            config: VaultConfig::default(),
+        })
+        .expect("Failed to serialize");
+
+        let ix = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new(authority.pubkey(), true),
+                AccountMeta::new(vault_state_pda, false),
+                AccountMeta::new(program::ID, false),
+                AccountMeta::new(vault_account_pda, false),
+                AccountMeta::new_readonly(token_program_id, false),
+                AccountMeta::new_readonly(program::ID, false),
+                AccountMeta::new_readonly(associated_token_program_id, false),
+            ],
+            data: ix_data,
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&authority.pubkey()),
+            &[&authority],
+            svm.latest_blockhash(),
+        );
+
+        let result = svm.send_transaction(tx);
+        assert!(result.is_ok());
+
+        vault_state_pdas.push(vault_state_pda);
+    }
+
+    assert_ne!(vault_state_pdas[0], vault_state_pdas[1]);
+
+    // a deposit that pairs vault 0's state with vault 1's token account must fail
+    let (vault_1_account_pda, _) =
+        Pubkey::find_program_address(&[b"vault", vault_state_pdas[1].as_ref()], &program_id);
+
+    let ix_data = borsh::to_vec(&VaultInstruction::Deposit {
+        amount: LAMPORTS_PER_SOL,
+    })
+    .expect("Failed to serialize");
+
+    let ix = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new(vault_state_pdas[0], false),
+            AccountMeta::new(authority.pubkey(), false),
+            AccountMeta::new(vault_1_account_pda, false),
+            AccountMeta::new_readonly(token_program_id, false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: ix_data,
+    };
+
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&authority.pubkey()),
+        &[&authority],
+        svm.latest_blockhash(),
+    );
+
+    let result = svm.send_transaction(tx);
+    assert!(result.is_err());
+}
+
+#[test]
+pub fn test_invalid_instruction_error() {
+    let (mut svm, program_id, authority) = setup();
+
+    let ix = Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new(authority.pubkey(), true)],
+        data: vec![255],
+    };
+
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InvalidInstruction);
+}
+
+#[test]
+pub fn test_initialize_errors() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 0);
+    let (vault_account_pda, _) = vault_pda(&program_id, &vault_state_pda);
+
+    // authority did not sign
+    let payer = Keypair::new();
+    svm.airdrop(&payer.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let mut ix = initialize_ix(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        0,
+        true,
+    );
+    ix.accounts[0].is_signer = false;
+    let result = send(&mut svm, &[ix], &payer, &[&payer]);
+    assert_vault_error(result, VaultError::MissingRequiredSignature);
+
+    // state account derived for a different vault id
+    let mut ix = initialize_ix(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        0,
+        true,
+    );
+    ix.accounts[1].pubkey = state_pda(&program_id, &authority.pubkey(), 1).0;
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InvalidStatePda);
+
+    // native vault account is not the vault PDA
+    let ix = initialize_ix(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &Pubkey::new_unique(),
+        0,
+        true,
+    );
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InvalidVaultAccount);
+
+    // bumps other than the canonical ones, even for an already funded vault PDA
+    send(
+        &mut svm,
+        &[solana_system_interface::instruction::transfer(
+            &authority.pubkey(),
+            &vault_account_pda,
+            LAMPORTS_PER_SOL,
+        )],
+        &authority,
+        &[&authority],
+    )
+    .expect("transfer failed");
+    for (byte, error) in [
+        (1, VaultError::InvalidVaultAccount),
+        (2, VaultError::InvalidStatePda),
+    ] {
+        let mut ix = initialize_ix(
+            &program_id,
+            &authority.pubkey(),
+            &program::ID,
+            &vault_account_pda,
+            0,
+            true,
+        );
+        ix.data[byte] = ix.data[byte].wrapping_sub(1);
+        let result = send(&mut svm, &[ix], &authority, &[&authority]);
+        assert_vault_error(result, error);
+    }
+
+    // spl vault with the wrong token program
+    let mut ix = initialize_ix(
+        &program_id,
+        &authority.pubkey(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+        0,
+        false,
+    );
+    ix.accounts[4].pubkey = Pubkey::new_unique();
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InvalidTokenProgram);
+
+    // same vault initialized twice
+    create_native_vault(&mut svm, &program_id, &authority, 0);
+    let ix = initialize_ix(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        0,
+        true,
+    );
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::AlreadyInitialized);
+}
+
+#[test]
+pub fn test_deposit_errors() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, vault_account_pda) =
+        create_native_vault(&mut svm, &program_id, &authority, 0);
+    let deposit = VaultInstruction::Deposit {
+        amount: LAMPORTS_PER_SOL,
+    };
+
+    // state account not owned by the program
+    let ix = vault_ix(
+        &program_id,
+        &deposit,
+        &authority.pubkey(),
+        &authority.pubkey(),
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InvalidStateOwner);
+
+    // a copy of the state data at an address that is not the state PDA
+    let spoofed_state = Pubkey::new_unique();
+    let state_account = svm.get_account(&vault_state_pda).unwrap();
+    svm.set_account(spoofed_state, state_account).unwrap();
+    let ix = vault_ix(
+        &program_id,
+        &deposit,
+        &authority.pubkey(),
+        &spoofed_state,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InvalidStatePda);
+
+    // destination is not the vault account
+    let ix = vault_ix(
+        &program_id,
+        &deposit,
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &Pubkey::new_unique(),
+    );
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InvalidVaultAccount);
+
+    // wrong system program
+    let mut ix = vault_ix(
+        &program_id,
+        &deposit,
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    ix.accounts[5].pubkey = token_program_id();
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InvalidSystemProgram);
+}
+
+#[test]
+pub fn test_withdraw_errors() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, vault_token_account, token_mint, mint_authority) =
+        create_spl_vault(&mut svm, &program_id, &authority, 0, &token_program_id());
+    let authority_token_account = fund_token_account(
+        &mut svm,
+        &token_mint,
+        &mint_authority,
+        &authority.pubkey(),
+        LAMPORTS_PER_SOL,
+        &token_program_id(),
+    );
+    let withdraw = VaultInstruction::Withdraw { amount: 1 };
+
+    // signer is not the vault authority
+    let attacker = Keypair::new();
+    svm.airdrop(&attacker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let ix = with_token_mint(
+        vault_ix(
+            &program_id,
+            &withdraw,
+            &attacker.pubkey(),
+            &vault_state_pda,
+            &vault_token_account,
+            &authority_token_account,
+        ),
+        &token_program_id(),
+        &token_mint,
+    );
+    let result = send(&mut svm, &[ix], &attacker, &[&attacker]);
+    assert_vault_error(result, VaultError::Unauthorized);
+
+    // destination holds a different mint
+    let other_mint = create_mint(&mut svm, &mint_authority, &token_program_id());
+    let other_token_account = fund_token_account(
+        &mut svm,
+        &other_mint,
+        &mint_authority,
+        &authority.pubkey(),
+        0,
+        &token_program_id(),
+    );
+    let ix = with_token_mint(
+        vault_ix(
+            &program_id,
+            &withdraw,
+            &authority.pubkey(),
+            &vault_state_pda,
+            &vault_token_account,
+            &other_token_account,
+        ),
+        &token_program_id(),
+        &token_mint,
+    );
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::MintMismatch);
+
+    // mint account is not the vault's mint
+    let ix = with_token_mint(
+        vault_ix(
+            &program_id,
+            &withdraw,
+            &authority.pubkey(),
+            &vault_state_pda,
+            &vault_token_account,
+            &authority_token_account,
+        ),
+        &token_program_id(),
+        &other_mint,
+    );
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::MintMismatch);
+
+    // destination is not owned by the authority
+    let stranger_token_account = fund_token_account(
+        &mut svm,
+        &token_mint,
+        &mint_authority,
+        &attacker.pubkey(),
+        0,
+        &token_program_id(),
+    );
+    let ix = with_token_mint(
+        vault_ix(
+            &program_id,
+            &withdraw,
+            &authority.pubkey(),
+            &vault_state_pda,
+            &vault_token_account,
+            &stranger_token_account,
+        ),
+        &token_program_id(),
+        &token_mint,
+    );
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InvalidTokenAccountOwner);
+}
+
+#[test]
+pub fn test_close_errors() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, vault_token_account, token_mint, mint_authority) =
+        create_spl_vault(&mut svm, &program_id, &authority, 0, &token_program_id());
+    let authority_token_account = fund_token_account(
+        &mut svm,
+        &token_mint,
+        &mint_authority,
+        &authority.pubkey(),
+        LAMPORTS_PER_SOL,
+        &token_program_id(),
+    );
+
+    let close_ix = |destination: &Pubkey| {
+        with_token_mint(
+            vault_ix(
+                &program_id,
+                &VaultInstruction::Close,
+                &authority.pubkey(),
+                &vault_state_pda,
+                &vault_token_account,
+                destination,
+            ),
+            &token_program_id(),
+            &token_mint,
+        )
+    };
+
+    // wrong token program
+    let mut ix = close_ix(&authority_token_account);
+    ix.accounts[4].pubkey = Pubkey::new_unique();
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InvalidTokenProgram);
+
+    let ix = with_token_mint(
+        vault_ix(
+            &program_id,
+            &VaultInstruction::Deposit { amount: 1 },
+            &authority.pubkey(),
+            &vault_state_pda,
+            &authority_token_account,
+            &vault_token_account,
+        ),
+        &token_program_id(),
+        &token_mint,
+    );
+    send(&mut svm, &[ix], &authority, &[&authority]).expect("deposit failed");
+
+    // leftover tokens may only be swept to the authority's own account
+    let stranger_token_account = fund_token_account(
+        &mut svm,
+        &token_mint,
+        &mint_authority,
+        &Pubkey::new_unique(),
+        0,
+        &token_program_id(),
+    );
+    let result = send(
+        &mut svm,
+        &[close_ix(&stranger_token_account)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::InvalidTokenAccountOwner);
+
+    // a vault that still holds tokens sweeps them before closing
+    send(
+        &mut svm,
+        &[close_ix(&authority_token_account)],
+        &authority,
+        &[&authority],
+    )
+    .expect("close failed");
+    assert_eq!(
+        token_balance(&svm, &authority_token_account),
+        LAMPORTS_PER_SOL
+    );
+    assert!(svm
+        .get_account(&vault_token_account)
+        .is_none_or(|account| account.data.is_empty()));
+}
+
+#[test]
+pub fn test_state_account_type_and_version() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, vault_account_pda) =
+        create_native_vault(&mut svm, &program_id, &authority, 0);
+
+    let state_account = svm.get_account(&vault_state_pda).unwrap();
+    assert_eq!(state_account.data.len(), VaultState::LEN);
+
+    let vault_state = VaultState::unpack(&state_account.data).unwrap();
+    assert_eq!(vault_state.account_type, AccountType::VaultState);
+    assert_eq!(vault_state.version, VaultState::VERSION);
+    assert_eq!(vault_state.decimals, 9);
+    assert_eq!(borsh::to_vec(&vault_state).unwrap().len(), VaultState::LEN);
+
+    let deposit = vault_ix(
+        &program_id,
+        &VaultInstruction::Deposit { amount: 1 },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+
+    // wrong account type tag
+    let mut tampered = state_account.clone();
+    tampered.data[0] = AccountType::Uninitialized as u8;
+    svm.set_account(vault_state_pda, tampered).unwrap();
+    let result = send(
+        &mut svm,
+        std::slice::from_ref(&deposit),
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::InvalidAccountType);
+
+    // layout version from the future
+    let mut tampered = state_account.clone();
+    tampered.data[1] = VaultState::VERSION + 1;
+    svm.set_account(vault_state_pda, tampered).unwrap();
+    let result = send(&mut svm, &[deposit], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::UnsupportedStateVersion);
+}
+
+#[test]
+pub fn test_migrate_legacy_state() {
+    let (mut svm, program_id, authority) = setup();
+
+    let vault_id: u64 = 7;
+    let (vault_state_pda, state_bump) = state_pda(&program_id, &authority.pubkey(), vault_id);
+    let (vault_account_pda, vault_bump) = vault_pda(&program_id, &vault_state_pda);
+
+    // accounts as an unversioned build of the program would have left them
+    let legacy_state = LegacyVaultState {
+        authority: solana_program::pubkey::Pubkey::new_from_array(authority.pubkey().to_bytes()),
+        token_mint: solana_program::pubkey::Pubkey::new_from_array(program::ID.to_bytes()),
+        token_account: solana_program::pubkey::Pubkey::new_from_array(vault_account_pda.to_bytes()),
+        state_bump,
+        vault_bump,
+        is_native: true,
+        vault_id,
+    };
+    let legacy_data = borsh::to_vec(&legacy_state).unwrap();
+    assert_eq!(legacy_data.len(), LegacyVaultState::LEN);
+
+    svm.set_account(
+        vault_state_pda,
+        Account {
+            lamports: Rent::default().minimum_balance(LegacyVaultState::LEN),
+            data: legacy_data,
+            owner: program_id,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+    svm.set_account(
+        vault_account_pda,
+        Account {
+            lamports: Rent::default().minimum_balance(0),
+            data: vec![],
+            owner: program::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+
+    let deposit = vault_ix(
+        &program_id,
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+
+    // the old layout is rejected until migrated
+    let result = send(
+        &mut svm,
+        std::slice::from_ref(&deposit),
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::InvalidStateData);
+
+    let migrate_ix = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::MigrateState).unwrap(),
+    };
+
+    // only the vault authority can migrate
+    let stranger = Keypair::new();
+    svm.airdrop(&stranger.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let mut stranger_ix = migrate_ix.clone();
+    stranger_ix.accounts[0].pubkey = stranger.pubkey();
+    let result = send(&mut svm, &[stranger_ix], &stranger, &[&stranger]);
+    assert_vault_error(result, VaultError::Unauthorized);
+
+    let result = send(
+        &mut svm,
+        std::slice::from_ref(&migrate_ix),
+        &authority,
+        &[&authority],
+    );
+    assert!(result.is_ok());
+
+    let state_account = svm.get_account(&vault_state_pda).unwrap();
+    assert_eq!(state_account.data.len(), VaultState::LEN);
+    assert!(state_account.lamports >= Rent::default().minimum_balance(VaultState::LEN));
+
+    let vault_state = VaultState::unpack(&state_account.data).unwrap();
+    assert_eq!(vault_state.version, VaultState::VERSION);
+    assert_eq!(vault_state.authority, legacy_state.authority);
+    assert_eq!(vault_state.token_account, legacy_state.token_account);
+    assert_eq!(vault_state.vault_id, vault_id);
+    assert_eq!(vault_state.creator, legacy_state.authority);
+    assert!(vault_state.is_native);
+
+    // migrating twice is rejected
+    let result = send(&mut svm, &[migrate_ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::AlreadyMigrated);
+
+    // the migrated vault is usable again
+    let result = send(&mut svm, &[deposit], &authority, &[&authority]);
+    assert!(result.is_ok());
+}
+
+#[test]
+pub fn test_migrate_original_state() {
+    let (mut svm, program_id, authority) = setup();
+
+    let mint_authority = Keypair::new();
+    svm.airdrop(&mint_authority.pubkey(), LAMPORTS_PER_SOL)
+        .unwrap();
+    let token_mint = create_mint(&mut svm, &mint_authority, &token_program_id());
+
+    // the first release derived the state PDA from the authority alone
+    let (vault_state_pda, state_bump) =
+        Pubkey::find_program_address(&[b"state", authority.pubkey().as_ref()], &program_id);
+    let vault_token_account = fund_token_account(
+        &mut svm,
+        &token_mint,
+        &mint_authority,
+        &vault_state_pda,
+        LAMPORTS_PER_SOL,
+        &token_program_id(),
+    );
+    let authority_token_account = fund_token_account(
+        &mut svm,
+        &token_mint,
+        &mint_authority,
+        &authority.pubkey(),
+        0,
+        &token_program_id(),
+    );
+
+    let original_state = OriginalVaultState {
+        authority: solana_program::pubkey::Pubkey::new_from_array(authority.pubkey().to_bytes()),
+        token_mint: solana_program::pubkey::Pubkey::new_from_array(token_mint.to_bytes()),
+        token_account: solana_program::pubkey::Pubkey::new_from_array(
+            vault_token_account.to_bytes(),
+        ),
+        state_bump,
+        vault_bump: 0,
+        is_native: false,
+    };
+    let original_data = borsh::to_vec(&original_state).unwrap();
+    assert_eq!(original_data.len(), 99);
+
+    svm.set_account(
+        vault_state_pda,
+        Account {
+            lamports: Rent::default().minimum_balance(OriginalVaultState::LEN),
+            data: original_data,
+            owner: program_id,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+
+    let migrate_ix = |vault_state: &Pubkey, token_mint: &Pubkey| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new(*vault_state, false),
+            AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new_readonly(*token_mint, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::MigrateState).unwrap(),
+    };
+    let result = send(
+        &mut svm,
+        &[migrate_ix(&vault_state_pda, &token_mint)],
+        &authority,
+        &[&authority],
+    );
+    assert!(result.is_ok());
+
+    let vault_state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert!(vault_state.legacy_address);
+    assert_eq!(vault_state.decimals, 9);
+    assert_eq!(vault_state.vault_id, 0);
+    assert_eq!(vault_state.creator, original_state.authority);
+    assert_eq!(vault_state.token_account, original_state.token_account);
+
+    // the vault still signs for its original address
+    let withdraw = with_token_mint(
+        vault_ix(
+            &program_id,
+            &VaultInstruction::Withdraw {
+                amount: LAMPORTS_PER_SOL / 2,
+            },
+            &authority.pubkey(),
+            &vault_state_pda,
+            &vault_token_account,
+            &authority_token_account,
+        ),
+        &token_program_id(),
+        &token_mint,
+    );
+    let result = send(&mut svm, &[withdraw], &authority, &[&authority]);
+    assert!(result.is_ok());
+    assert_eq!(
+        token_balance(&svm, &vault_token_account),
+        LAMPORTS_PER_SOL / 2
+    );
+    assert_eq!(
+        token_balance(&svm, &authority_token_account),
+        LAMPORTS_PER_SOL / 2
+    );
+
+    let result = send(
+        &mut svm,
+        &[migrate_ix(&vault_state_pda, &token_mint)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::AlreadyMigrated);
+
+    // a current vault from before decimals were recorded reads 0 until
+    // migrated
+    let (current_state_pda, _, current_mint, _) =
+        create_spl_vault(&mut svm, &program_id, &authority, 1, &token_program_id());
+    let mut account = svm.get_account(&current_state_pda).unwrap();
+    let mut state = VaultState::unpack(&account.data).unwrap();
+    state.decimals = 0;
+    state.pack(&mut account.data).unwrap();
+    svm.set_account(current_state_pda, account).unwrap();
+
+    let result = send(
+        &mut svm,
+        &[migrate_ix(&current_state_pda, &token_mint)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::MintMismatch);
+
+    let result = send(
+        &mut svm,
+        &[migrate_ix(&current_state_pda, &current_mint)],
+        &authority,
+        &[&authority],
+    );
+    assert!(result.is_ok());
+    let state = VaultState::unpack(&svm.get_account(&current_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.decimals, 9);
+}
+
+#[test]
+pub fn test_authority_transfer() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, vault_account_pda) =
+        create_native_vault(&mut svm, &program_id, &authority, 0);
+
+    let ix = vault_ix(
+        &program_id,
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    send(&mut svm, &[ix], &authority, &[&authority]).expect("deposit failed");
+
+    let new_authority = Keypair::new();
+    svm.airdrop(&new_authority.pubkey(), LAMPORTS_PER_SOL)
+        .unwrap();
+
+    let accept_ix = |signer: &Pubkey| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*signer, true),
+            AccountMeta::new(vault_state_pda, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::AcceptAuthority).unwrap(),
+    };
+
+    // nothing to accept yet
+    let result = send(
+        &mut svm,
+        &[accept_ix(&new_authority.pubkey())],
+        &new_authority,
+        &[&new_authority],
+    );
+    assert_vault_error(result, VaultError::NoPendingAuthority);
+
+    let propose_ix = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::ProposeAuthority {
+            new_authority: solana_program::pubkey::Pubkey::new_from_array(
+                new_authority.pubkey().to_bytes(),
+            ),
+        })
+        .unwrap(),
+    };
+    send(&mut svm, &[propose_ix], &authority, &[&authority]).expect("propose failed");
+
+    // only the proposed key can accept
+    let stranger = Keypair::new();
+    svm.airdrop(&stranger.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let result = send(
+        &mut svm,
+        &[accept_ix(&stranger.pubkey())],
+        &stranger,
+        &[&stranger],
+    );
+    assert_vault_error(result, VaultError::Unauthorized);
+
+    send(
+        &mut svm,
+        &[accept_ix(&new_authority.pubkey())],
+        &new_authority,
+        &[&new_authority],
+    )
+    .expect("accept failed");
+
+    let vault_state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(
+        vault_state.authority.to_bytes(),
+        new_authority.pubkey().to_bytes()
+    );
+    assert_eq!(
+        vault_state.creator.to_bytes(),
+        authority.pubkey().to_bytes()
+    );
+    assert!(!vault_state.has_pending_authority());
+
+    let withdraw = VaultInstruction::Withdraw {
+        amount: LAMPORTS_PER_SOL / 2,
+    };
+
+    // the previous authority has lost control
+    let ix = vault_ix(
+        &program_id,
+        &withdraw,
+        &authority.pubkey(),
+        &vault_state_pda,
+        &vault_account_pda,
+        &authority.pubkey(),
+    );
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::Unauthorized);
+
+    // the state PDA is still seeded by the original authority, but the new one signs
+    let ix = vault_ix(
+        &program_id,
+        &withdraw,
+        &new_authority.pubkey(),
+        &vault_state_pda,
+        &vault_account_pda,
+        &new_authority.pubkey(),
+    );
+    let result = send(&mut svm, &[ix], &new_authority, &[&new_authority]);
+    assert!(result.is_ok());
+}
+
+#[test]
+pub fn test_multisig_withdraw() {
+    let (mut svm, program_id, authority) = setup();
+
+    let members: Vec<Keypair> = (0..3).map(|_| Keypair::new()).collect();
+    for member in &members {
+        svm.airdrop(&member.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    }
+
+    let config = VaultConfig {
+        signers: members
+            .iter()
+            .map(|member| {
+                solana_program::pubkey::Pubkey::new_from_array(member.pubkey().to_bytes())
+            })
+            .collect(),
+        threshold: 2,
+        ..VaultConfig::default()
+    };
+
+    let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 0);
+    let (vault_account_pda, _) = vault_pda(&program_id, &vault_state_pda);
+
+    // threshold above the signer count is rejected
+    let ix = initialize_ix_with_config(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        0,
+        true,
+        VaultConfig {
+            threshold: 4,
+            ..config.clone()
+        },
+    );
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InvalidMultisigConfig);
+
+    let ix = initialize_ix_with_config(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        0,
+        true,
+        config,
+    );
+    send(&mut svm, &[ix], &authority, &[&authority]).expect("initialize failed");
+
+    let ix = vault_ix(
+        &program_id,
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    send(&mut svm, &[ix], &authority, &[&authority]).expect("deposit failed");
+
+    let withdraw_ix = |signers: &[&Keypair]| {
+        let mut ix = vault_ix(
+            &program_id,
+            &VaultInstruction::Withdraw {
+                amount: LAMPORTS_PER_SOL / 2,
+            },
+            &authority.pubkey(),
+            &vault_state_pda,
+            &vault_account_pda,
+            &authority.pubkey(),
+        );
+        // the authority key identifies the vault but does not approve on its own
+        ix.accounts[0].is_signer = false;
+        for signer in signers {
+            ix.accounts
+                .push(AccountMeta::new_readonly(signer.pubkey(), true));
+        }
+        ix
+    };
+
+    // one approval is below the threshold
+    let result = send(
+        &mut svm,
+        &[withdraw_ix(&[&members[0]])],
+        &members[0],
+        &[&members[0]],
+    );
+    assert_vault_error(result, VaultError::NotEnoughSigners);
+
+    // a non-member signature does not count
+    let outsider = Keypair::new();
+    svm.airdrop(&outsider.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let result = send(
+        &mut svm,
+        &[withdraw_ix(&[&members[0], &outsider])],
+        &members[0],
+        &[&members[0], &outsider],
+    );
+    assert_vault_error(result, VaultError::NotEnoughSigners);
+
+    let result = send(
+        &mut svm,
+        &[withdraw_ix(&[&members[0], &members[2]])],
+        &members[0],
+        &[&members[0], &members[2]],
+    );
+    assert!(result.is_ok());
+}
+
+#[test]
+pub fn test_withdrawal_proposals() {
+    let (mut svm, program_id, authority) = setup();
+
+    let members: Vec<Keypair> = (0..3).map(|_| Keypair::new()).collect();
+    let (vault_state_pda, vault_account_pda) =
+        create_multisig_native_vault(&mut svm, &program_id, &authority, &members, 2);
+
+    let proposal_pda = |proposal_id: u64| {
+        Pubkey::find_program_address(
+            &[
+                b"proposal",
+                vault_state_pda.as_ref(),
+                &0u64.to_le_bytes(),
+                &proposal_id.to_le_bytes(),
+            ],
+            &program_id,
+        )
+        .0
+    };
+
+    let destination = Pubkey::new_unique();
+
+    let create_ix = |proposer: &Keypair, proposal_id: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(proposer.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(proposal_pda(proposal_id), false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::CreateWithdrawalProposal {
+            amount: LAMPORTS_PER_SOL / 2,
+            destination: solana_program::pubkey::Pubkey::new_from_array(destination.to_bytes()),
+        })
+        .unwrap(),
+    };
+
+    let vote_ix = |voter: &Keypair, proposal_id: u64, instruction: VaultInstruction| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(voter.pubkey(), true),
+            AccountMeta::new_readonly(vault_state_pda, false),
+            AccountMeta::new(proposal_pda(proposal_id), false),
+        ],
+        data: borsh::to_vec(&instruction).unwrap(),
+    };
+
+    let execute_ix = |proposal_id: u64, destination: Pubkey| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(proposal_pda(proposal_id), false),
+            AccountMeta::new(vault_account_pda, false),
+            AccountMeta::new(destination, false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::ExecuteProposal).unwrap(),
+    };
+
+    // outsiders cannot propose
+    let outsider = Keypair::new();
+    svm.airdrop(&outsider.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let result = send(
+        &mut svm,
+        &[create_ix(&outsider, 0)],
+        &outsider,
+        &[&outsider],
+    );
+    assert_vault_error(result, VaultError::NotASigner);
+
+    send(
+        &mut svm,
+        &[create_ix(&members[0], 0)],
+        &members[0],
+        &[&members[0]],
+    )
+    .expect("create proposal failed");
+
+    // only the proposer has approved so far
+    let result = send(
+        &mut svm,
+        &[execute_ix(0, destination)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::NotEnoughSigners);
+
+    send(
+        &mut svm,
+        &[vote_ix(&members[1], 0, VaultInstruction::ApproveProposal)],
+        &members[1],
+        &[&members[1]],
+    )
+    .expect("approve failed");
+
+    // funds can only go to the proposed destination
+    let result = send(
+        &mut svm,
+        &[execute_ix(0, authority.pubkey())],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::InvalidDestination);
+
+    send(
+        &mut svm,
+        &[execute_ix(0, destination)],
+        &authority,
+        &[&authority],
+    )
+    .expect("execute failed");
+    assert_eq!(
+        svm.get_account(&destination).unwrap().lamports,
+        LAMPORTS_PER_SOL / 2
+    );
+
+    // a proposal executes only once
+    let result = send(
+        &mut svm,
+        &[execute_ix(0, destination)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::ProposalNotPending);
+
+    // two of three rejecting makes a 2-of-3 proposal unreachable
+    send(
+        &mut svm,
+        &[create_ix(&members[0], 1)],
+        &members[0],
+        &[&members[0]],
+    )
+    .expect("create proposal failed");
+    for member in &members[1..] {
+        send(
+            &mut svm,
+            &[vote_ix(member, 1, VaultInstruction::RejectProposal)],
+            member,
+            &[member],
+        )
+        .expect("reject failed");
+    }
+
+    let result = send(
+        &mut svm,
+        &[vote_ix(&members[1], 1, VaultInstruction::ApproveProposal)],
+        &members[1],
+        &[&members[1]],
+    );
+    assert_vault_error(result, VaultError::ProposalNotPending);
+}
+
+#[test]
+pub fn test_timelocked_withdrawals() {
+    let (mut svm, program_id, authority) = setup();
+
+    let delay: i64 = 3600;
+    let guardian = Keypair::new();
+    svm.airdrop(&guardian.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+    let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 0);
+    let (vault_account_pda, _) = vault_pda(&program_id, &vault_state_pda);
+
+    let initialize = initialize_ix_with_config(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        0,
+        true,
+        VaultConfig {
+            withdraw_delay: delay,
+            guardian: solana_program::pubkey::Pubkey::new_from_array(guardian.pubkey().to_bytes()),
+            ..VaultConfig::default()
+        },
+    );
+    let deposit = vault_ix(
+        &program_id,
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    send(&mut svm, &[initialize, deposit], &authority, &[&authority])
+        .expect("failed to create vault");
+
+    // instant withdrawals are disabled
+    let ix = vault_ix(
+        &program_id,
+        &VaultInstruction::Withdraw { amount: 1 },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &vault_account_pda,
+        &authority.pubkey(),
+    );
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::WithdrawTimelocked);
+
+    let destination = Pubkey::new_unique();
+    let pending_pda = |request_id: u64| {
+        Pubkey::find_program_address(
+            &[
+                b"withdrawal",
+                vault_state_pda.as_ref(),
+                &0u64.to_le_bytes(),
+                &request_id.to_le_bytes(),
+            ],
+            &program_id,
+        )
+        .0
+    };
+
+    let request_ix = |request_id: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(pending_pda(request_id), false),
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::RequestWithdraw {
+            amount: LAMPORTS_PER_SOL / 4,
+            destination: solana_program::pubkey::Pubkey::new_from_array(destination.to_bytes()),
+        })
+        .unwrap(),
+    };
+
+    let execute_ix = |request_id: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(pending_pda(request_id), false),
+            AccountMeta::new(vault_account_pda, false),
+            AccountMeta::new(destination, false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::ExecuteWithdraw).unwrap(),
+    };
+
+    let cancel_ix = |canceller: &Pubkey, request_id: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*canceller, true),
+            AccountMeta::new_readonly(vault_state_pda, false),
+            AccountMeta::new(pending_pda(request_id), false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::CancelWithdraw).unwrap(),
+    };
+
+    send(&mut svm, &[request_ix(0)], &authority, &[&authority]).expect("request failed");
+
+    let pending =
+        PendingWithdrawal::unpack(&svm.get_account(&pending_pda(0)).unwrap().data).unwrap();
+    assert_eq!(pending.status, WithdrawalStatus::Pending);
+    assert_eq!(
+        pending.requested_at,
+        svm.get_sysvar::<Clock>().unix_timestamp
+    );
+
+    // too early
+    let result = send(&mut svm, &[execute_ix(0)], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::TimelockNotElapsed);
+
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp += delay;
+    svm.set_sysvar(&clock);
+
+    send(&mut svm, &[execute_ix(0)], &authority, &[&authority]).expect("execute failed");
+    assert_eq!(
+        svm.get_account(&destination).unwrap().lamports,
+        LAMPORTS_PER_SOL / 4
+    );
+
+    // pre-funding the next request's PDA does not block it
+    let grief =
+        solana_system_interface::instruction::transfer(&guardian.pubkey(), &pending_pda(1), 1);
+    send(&mut svm, &[grief], &guardian, &[&guardian]).expect("pre-fund failed");
+
+    // a second request is cancelled by the guardian before it unlocks
+    send(&mut svm, &[request_ix(1)], &authority, &[&authority]).expect("request failed");
+    let pending_account = svm.get_account(&pending_pda(1)).unwrap();
+    assert_eq!(pending_account.owner, program_id);
+    assert_eq!(
+        PendingWithdrawal::unpack(&pending_account.data)
+            .unwrap()
+            .status,
+        WithdrawalStatus::Pending
+    );
+
+    let stranger = Keypair::new();
+    svm.airdrop(&stranger.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let result = send(
+        &mut svm,
+        &[cancel_ix(&stranger.pubkey(), 1)],
+        &stranger,
+        &[&stranger],
+    );
+    assert_vault_error(result, VaultError::Unauthorized);
+
+    send(
+        &mut svm,
+        &[cancel_ix(&guardian.pubkey(), 1)],
+        &guardian,
+        &[&guardian],
+    )
+    .expect("guardian cancel failed");
+
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp += delay;
+    svm.set_sysvar(&clock);
+
+    let result = send(&mut svm, &[execute_ix(1)], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::WithdrawalNotPending);
+
+    // vaults without a delay withdraw directly
+    let (instant_state_pda, _) = create_native_vault(&mut svm, &program_id, &authority, 1);
+    let mut ix = request_ix(0);
+    ix.accounts[1].pubkey = instant_state_pda;
+    ix.accounts[2].pubkey = Pubkey::find_program_address(
+        &[
+            b"withdrawal",
+            instant_state_pda.as_ref(),
+            &0u64.to_le_bytes(),
+            &0u64.to_le_bytes(),
+        ],
+        &program_id,
+    )
+    .0;
+    let result = send(&mut svm, &[ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::NotTimelocked);
+}
+
+#[test]
+pub fn test_timelocked_multisig_proposals() {
+    let (mut svm, program_id, authority) = setup();
+
+    let members: Vec<Keypair> = (0..2).map(|_| Keypair::new()).collect();
+    for member in &members {
+        svm.airdrop(&member.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    }
+
+    let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 0);
+    let (vault_account_pda, _) = vault_pda(&program_id, &vault_state_pda);
+
+    let initialize = initialize_ix_with_config(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        0,
+        true,
+        VaultConfig {
+            signers: members
+                .iter()
+                .map(|member| {
+                    solana_program::pubkey::Pubkey::new_from_array(member.pubkey().to_bytes())
+                })
+                .collect(),
+            threshold: 1,
+            withdraw_delay: 3600,
+            ..VaultConfig::default()
+        },
+    );
+    let deposit = vault_ix(
+        &program_id,
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    send(&mut svm, &[initialize, deposit], &authority, &[&authority])
+        .expect("failed to create vault");
+
+    let (proposal_pda, proposal_bump) = Pubkey::find_program_address(
+        &[
+            b"proposal",
+            vault_state_pda.as_ref(),
+            &0u64.to_le_bytes(),
+            &0u64.to_le_bytes(),
+        ],
+        &program_id,
+    );
+    let destination = Pubkey::new_unique();
+
+    // proposals would skip the delay, so they cannot be created
+    let create_ix = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(members[0].pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(proposal_pda, false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::CreateWithdrawalProposal {
+            amount: LAMPORTS_PER_SOL / 2,
+            destination: solana_program::pubkey::Pubkey::new_from_array(destination.to_bytes()),
+        })
+        .unwrap(),
+    };
+    let result = send(&mut svm, &[create_ix], &members[0], &[&members[0]]);
+    assert_vault_error(result, VaultError::WithdrawTimelocked);
+
+    // nor executed, even when already approved
+    let proposal = WithdrawalProposal {
+        account_type: AccountType::WithdrawalProposal,
+        vault_state: solana_program::pubkey::Pubkey::new_from_array(vault_state_pda.to_bytes()),
+        proposal_id: 0,
+        proposer: solana_program::pubkey::Pubkey::new_from_array(members[0].pubkey().to_bytes()),
+        amount: LAMPORTS_PER_SOL / 2,
+        destination: solana_program::pubkey::Pubkey::new_from_array(destination.to_bytes()),
+        approvals: 1,
+        rejections: 0,
+        status: ProposalStatus::Pending,
+        bump: proposal_bump,
+    };
+    svm.set_account(
+        proposal_pda,
+        Account {
+            lamports: Rent::default().minimum_balance(WithdrawalProposal::LEN),
+            data: borsh::to_vec(&proposal).unwrap(),
+            owner: program_id,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+
+    let execute_ix = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(proposal_pda, false),
+            AccountMeta::new(vault_account_pda, false),
+            AccountMeta::new(destination, false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::ExecuteProposal).unwrap(),
+    };
+    let result = send(&mut svm, &[execute_ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::WithdrawTimelocked);
+    assert!(svm.get_account(&destination).is_none());
+}
+
+#[test]
+pub fn test_withdraw_rate_limit() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, vault_account_pda) =
+        create_native_vault(&mut svm, &program_id, &authority, 0);
+
+    let deposit = vault_ix(
+        &program_id,
+        &VaultInstruction::Deposit {
+            amount: 2 * LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    send(&mut svm, &[deposit], &authority, &[&authority]).expect("deposit failed");
+
+    let set_rate_limit_ix = |rate_limit: RateLimit| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::SetRateLimit { rate_limit }).unwrap(),
+    };
+    let withdraw_ix = |amount: u64| {
+        vault_ix(
+            &program_id,
+            &VaultInstruction::Withdraw { amount },
+            &authority.pubkey(),
+            &vault_state_pda,
+            &vault_account_pda,
+            &authority.pubkey(),
+        )
+    };
+    let warp = |svm: &mut LiteSVM, seconds: i64| {
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += seconds;
+        svm.set_sysvar(&clock);
+    };
+
+    let window: i64 = 3600;
+
+    let result = send(
+        &mut svm,
+        &[set_rate_limit_ix(RateLimit {
+            period: RateLimitPeriod::Seconds,
+            max_amount: LAMPORTS_PER_SOL / 2,
+            window_seconds: 0,
+        })],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::InvalidRateLimit);
+
+    // the first limit on an unlimited vault applies immediately
+    send(
+        &mut svm,
+        &[set_rate_limit_ix(RateLimit {
+            period: RateLimitPeriod::Seconds,
+            max_amount: LAMPORTS_PER_SOL / 2,
+            window_seconds: window,
+        })],
+        &authority,
+        &[&authority],
+    )
+    .expect("set rate limit failed");
+
+    send(
+        &mut svm,
+        &[withdraw_ix(LAMPORTS_PER_SOL / 4)],
+        &authority,
+        &[&authority],
+    )
+    .expect("withdraw within limit failed");
+
+    let result = send(
+        &mut svm,
+        &[withdraw_ix(LAMPORTS_PER_SOL / 2)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::RateLimitExceeded);
+
+    // a new window resets the amount used
+    warp(&mut svm, window);
+    send(
+        &mut svm,
+        &[withdraw_ix(LAMPORTS_PER_SOL / 2)],
+        &authority,
+        &[&authority],
+    )
+    .expect("withdraw in new window failed");
+
+    // closing would sweep the rest past the limit
+    let close_ix = vault_ix(
+        &program_id,
+        &VaultInstruction::Close,
+        &authority.pubkey(),
+        &vault_state_pda,
+        &vault_account_pda,
+        &authority.pubkey(),
+    );
+    let result = send(&mut svm, &[close_ix], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::RateLimitExceeded);
+
+    // loosening the limit is queued
+    send(
+        &mut svm,
+        &[set_rate_limit_ix(RateLimit::default())],
+        &authority,
+        &[&authority],
+    )
+    .expect("queue rate limit change failed");
+
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.rate_limit.period, RateLimitPeriod::Seconds);
+    assert_eq!(state.pending_rate_limit, RateLimit::default());
+    assert_eq!(
+        state.pending_rate_limit_at,
+        svm.get_sysvar::<Clock>().unix_timestamp + RATE_LIMIT_CHANGE_DELAY
+    );
+
+    warp(&mut svm, window);
+    let result = send(
+        &mut svm,
+        &[withdraw_ix(LAMPORTS_PER_SOL)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::RateLimitExceeded);
+
+    warp(&mut svm, RATE_LIMIT_CHANGE_DELAY);
+    send(
+        &mut svm,
+        &[withdraw_ix(LAMPORTS_PER_SOL)],
+        &authority,
+        &[&authority],
+    )
+    .expect("withdraw after limit removed failed");
+
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.rate_limit, RateLimit::default());
+    assert_eq!(state.pending_rate_limit_at, 0);
+}
+
+#[test]
+pub fn test_pause() {
+    let (mut svm, program_id, authority) = setup();
+
+    let guardian = Keypair::new();
+    svm.airdrop(&guardian.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+    let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 0);
+    let (vault_account_pda, _) = vault_pda(&program_id, &vault_state_pda);
+
+    let initialize = initialize_ix_with_config(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        0,
+        true,
+        VaultConfig {
+            guardian: solana_program::pubkey::Pubkey::new_from_array(guardian.pubkey().to_bytes()),
+            ..VaultConfig::default()
+        },
+    );
+    send(&mut svm, &[initialize], &authority, &[&authority]).expect("failed to create vault");
+
+    let deposit_ix = || {
+        vault_ix(
+            &program_id,
+            &VaultInstruction::Deposit {
+                amount: LAMPORTS_PER_SOL,
+            },
+            &authority.pubkey(),
+            &vault_state_pda,
+            &authority.pubkey(),
+            &vault_account_pda,
+        )
+    };
+    let withdraw_ix = || {
+        vault_ix(
+            &program_id,
+            &VaultInstruction::Withdraw {
+                amount: LAMPORTS_PER_SOL / 2,
+            },
+            &authority.pubkey(),
+            &vault_state_pda,
+            &vault_account_pda,
+            &authority.pubkey(),
+        )
+    };
+    let admin_ix = |signer: &Pubkey, instruction: VaultInstruction| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*signer, true),
+            AccountMeta::new(vault_state_pda, false),
+        ],
+        data: borsh::to_vec(&instruction).unwrap(),
+    };
+
+    send(&mut svm, &[deposit_ix()], &authority, &[&authority]).expect("deposit failed");
+
+    let stranger = Keypair::new();
+    svm.airdrop(&stranger.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let result = send(
+        &mut svm,
+        &[admin_ix(&stranger.pubkey(), VaultInstruction::Pause)],
+        &stranger,
+        &[&stranger],
+    );
+    assert_vault_error(result, VaultError::Unauthorized);
+
+    send(
+        &mut svm,
+        &[admin_ix(&guardian.pubkey(), VaultInstruction::Pause)],
+        &guardian,
+        &[&guardian],
+    )
+    .expect("guardian pause failed");
+
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert!(state.paused);
+
+    let result = send(&mut svm, &[deposit_ix()], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::VaultPaused);
+
+    let result = send(&mut svm, &[withdraw_ix()], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::VaultPaused);
+
+    // the guardian cannot lift the pause
+    let result = send(
+        &mut svm,
+        &[admin_ix(&guardian.pubkey(), VaultInstruction::Unpause)],
+        &guardian,
+        &[&guardian],
+    );
+    assert_vault_error(result, VaultError::Unauthorized);
+
+    send(
+        &mut svm,
+        &[admin_ix(&authority.pubkey(), VaultInstruction::Unpause)],
+        &authority,
+        &[&authority],
+    )
+    .expect("unpause failed");
+
+    send(&mut svm, &[withdraw_ix()], &authority, &[&authority]).expect("withdraw failed");
+    send(&mut svm, &[deposit_ix()], &authority, &[&authority]).expect("deposit failed");
+}
+
+#[test]
+pub fn test_destination_allowlist() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, vault_account_pda) =
+        create_native_vault(&mut svm, &program_id, &authority, 0);
+
+    let deposit = vault_ix(
+        &program_id,
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    send(&mut svm, &[deposit], &authority, &[&authority]).expect("deposit failed");
+
+    let (allowlist_pda, _) = Pubkey::find_program_address(
+        &[b"allowlist", vault_state_pda.as_ref(), &0u64.to_le_bytes()],
+        &program_id,
+    );
+    let destination = Pubkey::new_unique();
+    let to_program_key =
+        |key: &Pubkey| solana_program::pubkey::Pubkey::new_from_array(key.to_bytes());
+
+    let add_ix = |destination: &Pubkey| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(allowlist_pda, false),
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::AddAllowedDestination {
+            destination: to_program_key(destination),
+        })
+        .unwrap(),
+    };
+    let remove_ix = |destination: &Pubkey| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new_readonly(vault_state_pda, false),
+            AccountMeta::new(allowlist_pda, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::RemoveAllowedDestination {
+            destination: to_program_key(destination),
+        })
+        .unwrap(),
+    };
+    let withdraw_ix = |destination: &Pubkey| {
+        let mut ix = vault_ix(
+            &program_id,
+            &VaultInstruction::Withdraw {
+                amount: LAMPORTS_PER_SOL / 4,
+            },
+            &authority.pubkey(),
+            &vault_state_pda,
+            &vault_account_pda,
+            destination,
+        );
+        ix.accounts
+            .push(AccountMeta::new_readonly(allowlist_pda, false));
+        ix
+    };
+
+    send(&mut svm, &[add_ix(&destination)], &authority, &[&authority])
+        .expect("add destination failed");
+
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert!(state.allowlist_enabled);
+
+    let allowlist_account = svm.get_account(&allowlist_pda).unwrap();
+    assert_eq!(
+        allowlist_account.data.len(),
+        DestinationAllowlist::BASE_LEN + AllowedDestination::LEN
+    );
+    let allowlist = DestinationAllowlist::unpack(&allowlist_account.data).unwrap();
+    assert_eq!(allowlist.entries.len(), 1);
+
+    let result = send(&mut svm, &[add_ix(&destination)], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::AllowlistEntryExists);
+
+    // additions only take effect after the delay
+    let result = send(
+        &mut svm,
+        &[withdraw_ix(&destination)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::DestinationNotAllowed);
+
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp += ALLOWLIST_ADDITION_DELAY;
+    svm.set_sysvar(&clock);
+
+    send(
+        &mut svm,
+        &[withdraw_ix(&destination)],
+        &authority,
+        &[&authority],
+    )
+    .expect("withdraw to allowed destination failed");
+    assert_eq!(
+        svm.get_account(&destination).unwrap().lamports,
+        LAMPORTS_PER_SOL / 4
+    );
+
+    // unlisted destinations are rejected
+    let result = send(
+        &mut svm,
+        &[withdraw_ix(&authority.pubkey())],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::DestinationNotAllowed);
+
+    // removals take effect immediately
+    send(
+        &mut svm,
+        &[remove_ix(&destination)],
+        &authority,
+        &[&authority],
+    )
+    .expect("remove destination failed");
+    assert_eq!(
+        svm.get_account(&allowlist_pda).unwrap().data.len(),
+        DestinationAllowlist::BASE_LEN
+    );
+
+    let result = send(
+        &mut svm,
+        &[withdraw_ix(&destination)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::DestinationNotAllowed);
+
+    let result = send(
+        &mut svm,
+        &[remove_ix(&destination)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::AllowlistEntryNotFound);
+}
+
+#[test]
+pub fn test_allowlist_on_proposals_and_queued_withdrawals() {
+    let (mut svm, program_id, authority) = setup();
+
+    let to_program_key =
+        |key: &Pubkey| solana_program::pubkey::Pubkey::new_from_array(key.to_bytes());
+    let allowed = Pubkey::new_unique();
+    let unlisted = Pubkey::new_unique();
+
+    let allowlist_pda = |vault_state: &Pubkey| {
+        Pubkey::find_program_address(
+            &[b"allowlist", vault_state.as_ref(), &0u64.to_le_bytes()],
+            &program_id,
+        )
+        .0
+    };
+    let add_ix = |vault_state: &Pubkey, signer: &Pubkey| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), false),
+            AccountMeta::new(*vault_state, false),
+            AccountMeta::new(allowlist_pda(vault_state), false),
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new_readonly(*signer, true),
+        ],
+        data: borsh::to_vec(&VaultInstruction::AddAllowedDestination {
+            destination: to_program_key(&allowed),
+        })
+        .unwrap(),
+    };
+
+    // a 1-of-2 multisig vault, whose proposals execute once created
+    let members: Vec<Keypair> = (0..2).map(|_| Keypair::new()).collect();
+    let (multisig_state_pda, multisig_vault_pda) =
+        create_multisig_native_vault(&mut svm, &program_id, &authority, &members, 1);
+    send(
+        &mut svm,
+        &[add_ix(&multisig_state_pda, &members[0].pubkey())],
+        &authority,
+        &[&authority, &members[0]],
+    )
+    .expect("add destination failed");
+
+    // a timelocked vault
+    let (timelocked_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 1);
+    let (timelocked_vault_pda, _) = vault_pda(&program_id, &timelocked_state_pda);
+    let delay: i64 = 3600;
+    let initialize = initialize_ix_with_config(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &timelocked_vault_pda,
+        1,
+        true,
+        VaultConfig {
+            withdraw_delay: delay,
+            ..VaultConfig::default()
+        },
+    );
+    let deposit = vault_ix(
+        &program_id,
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &timelocked_state_pda,
+        &authority.pubkey(),
+        &timelocked_vault_pda,
+    );
+    send(
+        &mut svm,
+        &[
+            initialize,
+            deposit,
+            add_ix(&timelocked_state_pda, &authority.pubkey()),
+        ],
+        &authority,
+        &[&authority],
+    )
+    .expect("failed to create timelocked vault");
+
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp += ALLOWLIST_ADDITION_DELAY;
+    svm.set_sysvar(&clock);
+
+    // proposals
+    let proposal_pda = |proposal_id: u64| {
+        Pubkey::find_program_address(
+            &[
+                b"proposal",
+                multisig_state_pda.as_ref(),
+                &0u64.to_le_bytes(),
+                &proposal_id.to_le_bytes(),
+            ],
+            &program_id,
+        )
+        .0
+    };
+    let propose = |svm: &mut LiteSVM, proposal_id: u64, destination: &Pubkey| {
+        let create_ix = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new(members[0].pubkey(), true),
+                AccountMeta::new(multisig_state_pda, false),
+                AccountMeta::new(proposal_pda(proposal_id), false),
+                AccountMeta::new_readonly(program::ID, false),
+            ],
+            data: borsh::to_vec(&VaultInstruction::CreateWithdrawalProposal {
+                amount: LAMPORTS_PER_SOL / 4,
+                destination: to_program_key(destination),
+            })
+            .unwrap(),
+        };
+        send(svm, &[create_ix], &members[0], &[&members[0]]).expect("create proposal failed");
+    };
+    let execute_proposal_ix = |proposal_id: u64, destination: &Pubkey| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(multisig_state_pda, false),
+            AccountMeta::new(proposal_pda(proposal_id), false),
+            AccountMeta::new(multisig_vault_pda, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new_readonly(allowlist_pda(&multisig_state_pda), false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::ExecuteProposal).unwrap(),
+    };
+
+    propose(&mut svm, 0, &unlisted);
+    let result = send(
+        &mut svm,
+        &[execute_proposal_ix(0, &unlisted)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::DestinationNotAllowed);
+
+    propose(&mut svm, 1, &allowed);
+    let result = send(
+        &mut svm,
+        &[execute_proposal_ix(1, &allowed)],
+        &authority,
+        &[&authority],
+    );
+    assert!(result.is_ok());
+    assert_eq!(
+        svm.get_account(&allowed).unwrap().lamports,
+        LAMPORTS_PER_SOL / 4
+    );
+
+    // queued withdrawals
+    let pending_pda = |request_id: u64| {
+        Pubkey::find_program_address(
+            &[
+                b"withdrawal",
+                timelocked_state_pda.as_ref(),
+                &0u64.to_le_bytes(),
+                &request_id.to_le_bytes(),
+            ],
+            &program_id,
+        )
+        .0
+    };
+    for (request_id, destination) in [(0u64, &unlisted), (1, &allowed)] {
+        let request_ix = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(authority.pubkey(), true),
+                AccountMeta::new(timelocked_state_pda, false),
+                AccountMeta::new(pending_pda(request_id), false),
+                AccountMeta::new(authority.pubkey(), true),
+                AccountMeta::new_readonly(program::ID, false),
+            ],
+            data: borsh::to_vec(&VaultInstruction::RequestWithdraw {
+                amount: LAMPORTS_PER_SOL / 4,
+                destination: to_program_key(destination),
+            })
+            .unwrap(),
+        };
+        send(&mut svm, &[request_ix], &authority, &[&authority]).expect("request failed");
+    }
+
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp += delay;
+    svm.set_sysvar(&clock);
+
+    let execute_ix = |request_id: u64, destination: &Pubkey| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(timelocked_state_pda, false),
+            AccountMeta::new(pending_pda(request_id), false),
+            AccountMeta::new(timelocked_vault_pda, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new_readonly(allowlist_pda(&timelocked_state_pda), false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::ExecuteWithdraw).unwrap(),
+    };
+
+    let result = send(
+        &mut svm,
+        &[execute_ix(0, &unlisted)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::DestinationNotAllowed);
+
+    let result = send(
+        &mut svm,
+        &[execute_ix(1, &allowed)],
+        &authority,
+        &[&authority],
+    );
+    assert!(result.is_ok());
+    assert_eq!(
+        svm.get_account(&allowed).unwrap().lamports,
+        LAMPORTS_PER_SOL / 2
+    );
+}
+
+#[test]
+pub fn test_delegated_withdraw() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, vault_account_pda) =
+        create_native_vault(&mut svm, &program_id, &authority, 0);
+
+    let deposit = vault_ix(
+        &program_id,
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    send(&mut svm, &[deposit], &authority, &[&authority]).expect("deposit failed");
+
+    let bot = Keypair::new();
+    svm.airdrop(&bot.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let (delegate_pda, _) = Pubkey::find_program_address(
+        &[
+            b"delegate",
+            vault_state_pda.as_ref(),
+            &0u64.to_le_bytes(),
+            bot.pubkey().as_ref(),
+        ],
+        &program_id,
+    );
+    let destination = Pubkey::new_unique();
+
+    let approve_ix = |allowance: u64, expires_at: i64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new_readonly(vault_state_pda, false),
+            AccountMeta::new(delegate_pda, false),
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::ApproveDelegate {
+            delegate: solana_program::pubkey::Pubkey::new_from_array(bot.pubkey().to_bytes()),
+            allowance,
+            expires_at,
+        })
+        .unwrap(),
+    };
+    let delegated_withdraw_ix = |signer: &Pubkey, amount: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*signer, true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(delegate_pda, false),
+            AccountMeta::new(vault_account_pda, false),
+            AccountMeta::new(destination, false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::DelegatedWithdraw { amount }).unwrap(),
+    };
+
+    let expires_at = svm.get_sysvar::<Clock>().unix_timestamp + 3600;
+    send(
+        &mut svm,
+        &[approve_ix(LAMPORTS_PER_SOL / 2, expires_at)],
+        &authority,
+        &[&authority],
+    )
+    .expect("approve delegate failed");
+
+    let delegation = Delegate::unpack(&svm.get_account(&delegate_pda).unwrap().data).unwrap();
+    assert_eq!(delegation.allowance, LAMPORTS_PER_SOL / 2);
+    assert_eq!(delegation.expires_at, expires_at);
+
+    send(
+        &mut svm,
+        &[delegated_withdraw_ix(&bot.pubkey(), LAMPORTS_PER_SOL / 4)],
+        &bot,
+        &[&bot],
+    )
+    .expect("delegated withdraw failed");
+    assert_eq!(
+        svm.get_account(&destination).unwrap().lamports,
+        LAMPORTS_PER_SOL / 4
+    );
+
+    let result = send(
+        &mut svm,
+        &[delegated_withdraw_ix(&bot.pubkey(), LAMPORTS_PER_SOL / 2)],
+        &bot,
+        &[&bot],
+    );
+    assert_vault_error(result, VaultError::AllowanceExceeded);
+
+    let stranger = Keypair::new();
+    svm.airdrop(&stranger.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let result = send(
+        &mut svm,
+        &[delegated_withdraw_ix(&stranger.pubkey(), 1)],
+        &stranger,
+        &[&stranger],
+    );
+    assert_vault_error(result, VaultError::InvalidDelegate);
+
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp = expires_at;
+    svm.set_sysvar(&clock);
+
+    let result = send(
+        &mut svm,
+        &[delegated_withdraw_ix(&bot.pubkey(), 1)],
+        &bot,
+        &[&bot],
+    );
+    assert_vault_error(result, VaultError::DelegateExpired);
+
+    // revoking closes the delegate account
+    let revoke = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new_readonly(vault_state_pda, false),
+            AccountMeta::new(delegate_pda, false),
+            AccountMeta::new(authority.pubkey(), false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::RevokeDelegate).unwrap(),
+    };
+    send(&mut svm, &[revoke], &authority, &[&authority]).expect("revoke failed");
+    assert!(svm
+        .get_account(&delegate_pda)
+        .is_none_or(|account| account.lamports == 0));
+
+    let result = send(
+        &mut svm,
+        &[delegated_withdraw_ix(&bot.pubkey(), 1)],
+        &bot,
+        &[&bot],
+    );
+    assert_vault_error(result, VaultError::InvalidDelegate);
+}
+
+#[test]
+pub fn test_pooled_vault() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 0);
+    let (vault_account_pda, _) = vault_pda(&program_id, &vault_state_pda);
+    let (share_mint, _) = Pubkey::find_program_address(
+        &[b"shares", vault_state_pda.as_ref(), &0u64.to_le_bytes()],
+        &program_id,
+    );
+
+    let mut initialize = initialize_ix_with_config(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        0,
+        true,
+        VaultConfig {
+            pooled: true,
+            ..VaultConfig::default()
+        },
+    );
+    initialize
+        .accounts
+        .push(AccountMeta::new(share_mint, false));
+    send(&mut svm, &[initialize], &authority, &[&authority]).expect("failed to create vault");
+
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.share_mint.to_bytes(), share_mint.to_bytes());
+    let share_mint_state =
+        spl_token::state::Mint::unpack(&svm.get_account(&share_mint).unwrap().data).unwrap();
+    assert_eq!(share_mint_state.decimals, 9 + SHARE_DECIMALS_OFFSET);
+
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    for user in [&alice, &bob] {
+        svm.airdrop(&user.pubkey(), 5 * LAMPORTS_PER_SOL).unwrap();
+        send(
+            &mut svm,
+            &[create_ata_ix(
+                &user.pubkey(),
+                &user.pubkey(),
+                &share_mint,
+                &token_program_id(),
+            )],
+            user,
+            &[user],
+        )
+        .expect("failed to create share account");
+    }
+
+    let deposit_ix = |depositor: &Pubkey, amount: u64| {
+        let mut ix = vault_ix(
+            &program_id,
+            &VaultInstruction::Deposit { amount },
+            depositor,
+            &vault_state_pda,
+            depositor,
+            &vault_account_pda,
+        );
+        ix.accounts.push(AccountMeta::new(share_mint, false));
+        ix.accounts.push(AccountMeta::new(
+            ata(depositor, &share_mint, &token_program_id()),
+            false,
+        ));
+        ix
+    };
+    let redeem_ix = |owner: &Pubkey, shares: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(share_mint, false),
+            AccountMeta::new(ata(owner, &share_mint, &token_program_id()), false),
+            AccountMeta::new(vault_account_pda, false),
+            AccountMeta::new(*owner, false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::Redeem { shares }).unwrap(),
+    };
+    let share_balance = |svm: &LiteSVM, owner: &Pubkey| {
+        spl_token::state::Account::unpack(
+            &svm.get_account(&ata(owner, &share_mint, &token_program_id()))
+                .unwrap()
+                .data,
+        )
+        .unwrap()
+        .amount
+    };
+
+    // the first deposit mints at the virtual shares' rate
+    send(
+        &mut svm,
+        &[deposit_ix(&alice.pubkey(), LAMPORTS_PER_SOL)],
+        &alice,
+        &[&alice],
+    )
+    .expect("alice deposit failed");
+    let alice_shares = share_balance(&svm, &alice.pubkey());
+    assert_eq!(alice_shares, LAMPORTS_PER_SOL * VIRTUAL_SHARES);
+
+    // the authority cannot take the pool's assets
+    let withdraw = vault_ix(
+        &program_id,
+        &VaultInstruction::Withdraw { amount: 1 },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &vault_account_pda,
+        &authority.pubkey(),
+    );
+    let result = send(&mut svm, &[withdraw], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::PooledVault);
+
+    let withdraw_all = vault_ix(
+        &program_id,
+        &VaultInstruction::WithdrawAll,
+        &authority.pubkey(),
+        &vault_state_pda,
+        &vault_account_pda,
+        &authority.pubkey(),
+    );
+    let result = send(&mut svm, &[withdraw_all], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::PooledVault);
+
+    // the vault gains 50% before bob deposits, so his shares cost more
+    let vault_lamports = svm.get_account(&vault_account_pda).unwrap().lamports;
+    svm.airdrop(&vault_account_pda, LAMPORTS_PER_SOL / 2)
+        .unwrap();
+    assert_eq!(
+        svm.get_account(&vault_account_pda).unwrap().lamports,
+        vault_lamports + LAMPORTS_PER_SOL / 2
+    );
+
+    send(
+        &mut svm,
+        &[deposit_ix(&bob.pubkey(), 3 * LAMPORTS_PER_SOL / 2)],
+        &bob,
+        &[&bob],
+    )
+    .expect("bob deposit failed");
+    let bob_shares = share_balance(&svm, &bob.pubkey());
+    assert!(bob_shares.abs_diff(alice_shares) < alice_shares / 1_000_000);
+
+    // redemptions too small to pay out are rejected rather than lost
+    let result = send(&mut svm, &[redeem_ix(&bob.pubkey(), 1)], &bob, &[&bob]);
+    assert_vault_error(result, VaultError::ZeroShares);
+
+    // redemptions count against the rate limit
+    let set_rate_limit = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::SetRateLimit {
+            rate_limit: RateLimit {
+                period: RateLimitPeriod::Seconds,
+                max_amount: 3 * LAMPORTS_PER_SOL / 2,
+                window_seconds: 3600,
+            },
+        })
+        .unwrap(),
+    };
+    send(&mut svm, &[set_rate_limit], &authority, &[&authority]).expect("set rate limit failed");
+
+    let alice_lamports = svm.get_account(&alice.pubkey()).unwrap().lamports;
+    send(
+        &mut svm,
+        &[redeem_ix(&alice.pubkey(), alice_shares)],
+        &alice,
+        &[&alice],
+    )
+    .expect("alice redeem failed");
+    assert_eq!(share_balance(&svm, &alice.pubkey()), 0);
+    // alice receives her deposit plus the gain, less the transaction fee
+    assert!(
+        svm.get_account(&alice.pubkey()).unwrap().lamports
+            > alice_lamports + 3 * LAMPORTS_PER_SOL / 2 - LAMPORTS_PER_SOL / 1000
+    );
+
+    let result = send(
+        &mut svm,
+        &[redeem_ix(&bob.pubkey(), bob_shares)],
+        &bob,
+        &[&bob],
+    );
+    assert_vault_error(result, VaultError::RateLimitExceeded);
+
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp += 3600;
+    svm.set_sysvar(&clock);
+
+    send(
+        &mut svm,
+        &[redeem_ix(&bob.pubkey(), bob_shares)],
+        &bob,
+        &[&bob],
+    )
+    .expect("bob redeem failed");
+    // only rounding dust is left behind
+    assert!(svm.get_account(&vault_account_pda).unwrap().lamports <= vault_lamports + 1);
+
+    // redeeming more shares than owned fails in the token program
+    let result = send(&mut svm, &[redeem_ix(&bob.pubkey(), 1)], &bob, &[&bob]);
+    assert!(result.is_err());
+}
+
+#[test]
+pub fn test_pooled_vault_inflation() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 0);
+    let (vault_account_pda, _) = vault_pda(&program_id, &vault_state_pda);
+    let (share_mint, _) = Pubkey::find_program_address(
+        &[b"shares", vault_state_pda.as_ref(), &0u64.to_le_bytes()],
+        &program_id,
+    );
+
+    let mut initialize = initialize_ix_with_config(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        0,
+        true,
+        VaultConfig {
+            pooled: true,
+            ..VaultConfig::default()
+        },
+    );
+    initialize
+        .accounts
+        .push(AccountMeta::new(share_mint, false));
+    send(&mut svm, &[initialize], &authority, &[&authority]).expect("failed to create vault");
+
+    let attacker = Keypair::new();
+    let victim = Keypair::new();
+    for user in [&attacker, &victim] {
+        svm.airdrop(&user.pubkey(), 20 * LAMPORTS_PER_SOL).unwrap();
+        send(
+            &mut svm,
+            &[create_ata_ix(
+                &user.pubkey(),
+                &user.pubkey(),
+                &share_mint,
+                &token_program_id(),
+            )],
+            user,
+            &[user],
+        )
+        .expect("failed to create share account");
+    }
+
+    let deposit_ix = |depositor: &Pubkey, amount: u64| {
+        let mut ix = vault_ix(
+            &program_id,
+            &VaultInstruction::Deposit { amount },
+            depositor,
+            &vault_state_pda,
+            depositor,
+            &vault_account_pda,
+        );
+        ix.accounts.push(AccountMeta::new(share_mint, false));
+        ix.accounts.push(AccountMeta::new(
+            ata(depositor, &share_mint, &token_program_id()),
+            false,
+        ));
+        ix
+    };
+    let redeem_ix = |owner: &Pubkey, shares: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(share_mint, false),
+            AccountMeta::new(ata(owner, &share_mint, &token_program_id()), false),
+            AccountMeta::new(vault_account_pda, false),
+            AccountMeta::new(*owner, false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::Redeem { shares }).unwrap(),
+    };
+    let share_balance = |svm: &LiteSVM, owner: &Pubkey| {
+        spl_token::state::Account::unpack(
+            &svm.get_account(&ata(owner, &share_mint, &token_program_id()))
+                .unwrap()
+                .data,
+        )
+        .unwrap()
+        .amount
+    };
+
+    // the attacker takes the first shares with a single lamport, then donates
+    // to the vault so the next deposit rounds down
+    send(
+        &mut svm,
+        &[deposit_ix(&attacker.pubkey(), 1)],
+        &attacker,
+        &[&attacker],
+    )
+    .expect("attacker deposit failed");
+    let donation = 10 * LAMPORTS_PER_SOL;
+    svm.airdrop(&vault_account_pda, donation).unwrap();
+
+    let victim_lamports = svm.get_account(&victim.pubkey()).unwrap().lamports;
+    send(
+        &mut svm,
+        &[deposit_ix(&victim.pubkey(), 15 * LAMPORTS_PER_SOL)],
+        &victim,
+        &[&victim],
+    )
+    .expect("victim deposit failed");
+    let victim_shares = share_balance(&svm, &victim.pubkey());
+    send(
+        &mut svm,
+        &[redeem_ix(&victim.pubkey(), victim_shares)],
+        &victim,
+        &[&victim],
+    )
+    .expect("victim redeem failed");
+
+    // the victim loses no more than rounding
+    assert!(
+        svm.get_account(&victim.pubkey()).unwrap().lamports
+            > victim_lamports - LAMPORTS_PER_SOL / 100
+    );
+
+    // and the attacker cannot recover the donation
+    let attacker_lamports = svm.get_account(&attacker.pubkey()).unwrap().lamports;
+    let attacker_shares = share_balance(&svm, &attacker.pubkey());
+    send(
+        &mut svm,
+        &[redeem_ix(&attacker.pubkey(), attacker_shares)],
+        &attacker,
+        &[&attacker],
+    )
+    .expect("attacker redeem failed");
+    assert!(
+        svm.get_account(&attacker.pubkey()).unwrap().lamports
+            < attacker_lamports + donation / 2 + LAMPORTS_PER_SOL / 100
+    );
+}
+
+#[test]
+pub fn test_depositor_receipts() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 0);
+    let (vault_account_pda, _) = vault_pda(&program_id, &vault_state_pda);
+
+    let initialize = initialize_ix_with_config(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        0,
+        true,
+        VaultConfig {
+            refundable: true,
+            ..VaultConfig::default()
+        },
+    );
+    send(&mut svm, &[initialize], &authority, &[&authority]).expect("failed to create vault");
+
+    let depositor = Keypair::new();
+    svm.airdrop(&depositor.pubkey(), 5 * LAMPORTS_PER_SOL)
+        .unwrap();
+    let receipt_pda = |vault_state: &Pubkey, depositor: &Pubkey| {
+        Pubkey::find_program_address(
+            &[
+                b"receipt",
+                vault_state.as_ref(),
+                &0u64.to_le_bytes(),
+                depositor.as_ref(),
+            ],
+            &program_id,
+        )
+        .0
+    };
+    let receipt = receipt_pda(&vault_state_pda, &depositor.pubkey());
+
+    let deposit_ix = |amount: u64| {
+        let mut ix = vault_ix(
+            &program_id,
+            &VaultInstruction::DepositWithReceipt { amount },
+            &depositor.pubkey(),
+            &vault_state_pda,
+            &depositor.pubkey(),
+            &vault_account_pda,
+        );
+        ix.accounts.push(AccountMeta::new(receipt, false));
+        ix
+    };
+    let withdraw_own_ix = |signer: &Pubkey, amount: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*signer, true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(receipt, false),
+            AccountMeta::new(vault_account_pda, false),
+            AccountMeta::new(*signer, false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::WithdrawOwn { amount }).unwrap(),
+    };
+
+    // refundable vaults record every deposit
+    let without_receipt = vault_ix(
+        &program_id,
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &depositor.pubkey(),
+        &vault_state_pda,
+        &depositor.pubkey(),
+        &vault_account_pda,
+    );
+    let result = send(&mut svm, &[without_receipt], &depositor, &[&depositor]);
+    assert_vault_error(result, VaultError::ReceiptRequired);
+
+    let mut missing_receipt = deposit_ix(LAMPORTS_PER_SOL);
+    missing_receipt.accounts.pop();
+    let result = send(&mut svm, &[missing_receipt], &depositor, &[&depositor]);
+    assert!(result.is_err());
+
+    // pre-funding the receipt PDA does not lock the depositor out
+    let grief = solana_system_interface::instruction::transfer(&authority.pubkey(), &receipt, 1);
+    send(&mut svm, &[grief], &authority, &[&authority]).expect("pre-fund failed");
+
+    send(
+        &mut svm,
+        &[deposit_ix(LAMPORTS_PER_SOL)],
+        &depositor,
+        &[&depositor],
+    )
+    .expect("first deposit failed");
+    assert_eq!(svm.get_account(&receipt).unwrap().owner, program_id);
+    send(
+        &mut svm,
+        &[deposit_ix(LAMPORTS_PER_SOL / 2)],
+        &depositor,
+        &[&depositor],
+    )
+    .expect("second deposit failed");
+
+    let recorded = DepositorReceipt::unpack(&svm.get_account(&receipt).unwrap().data).unwrap();
+    assert_eq!(recorded.total_deposited, 3 * LAMPORTS_PER_SOL / 2);
+    assert_eq!(recorded.balance, 3 * LAMPORTS_PER_SOL / 2);
+    assert_eq!(recorded.last_deposit_slot, svm.get_sysvar::<Clock>().slot);
+
+    // the authority cannot move funds the vault owes on receipts
+    let authority_withdraw = |instruction: &VaultInstruction| {
+        vault_ix(
+            &program_id,
+            instruction,
+            &authority.pubkey(),
+            &vault_state_pda,
+            &vault_account_pda,
+            &authority.pubkey(),
+        )
+    };
+    let result = send(
+        &mut svm,
+        &[authority_withdraw(&VaultInstruction::Withdraw {
+            amount: 1,
+        })],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::InsufficientVaultBalance);
+    let result = send(
+        &mut svm,
+        &[authority_withdraw(&VaultInstruction::WithdrawAll)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::InsufficientVaultBalance);
+
+    // only funds beyond the receipts are the authority's
+    let donation = solana_system_interface::instruction::transfer(
+        &authority.pubkey(),
+        &vault_account_pda,
+        LAMPORTS_PER_SOL / 4,
+    );
+    send(&mut svm, &[donation], &authority, &[&authority]).expect("donation failed");
+    let vault_lamports = svm.get_account(&vault_account_pda).unwrap().lamports;
+    send(
+        &mut svm,
+        &[authority_withdraw(&VaultInstruction::WithdrawAll)],
+        &authority,
+        &[&authority],
+    )
+    .expect("withdraw all failed");
+    assert_eq!(
+        svm.get_account(&vault_account_pda).unwrap().lamports,
+        vault_lamports - LAMPORTS_PER_SOL / 4
+    );
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.outstanding_receipts, 3 * LAMPORTS_PER_SOL / 2);
+
+    let result = send(
+        &mut svm,
+        &[withdraw_own_ix(&depositor.pubkey(), 2 * LAMPORTS_PER_SOL)],
+        &depositor,
+        &[&depositor],
+    );
+    assert_vault_error(result, VaultError::InsufficientReceiptBalance);
+
+    // another key cannot spend this receipt
+    let result = send(
+        &mut svm,
+        &[withdraw_own_ix(&authority.pubkey(), LAMPORTS_PER_SOL)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::InvalidReceipt);
+
+    let set_rate_limit = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::SetRateLimit {
+            rate_limit: RateLimit {
+                period: RateLimitPeriod::Seconds,
+                max_amount: LAMPORTS_PER_SOL,
+                window_seconds: 3600,
+            },
+        })
+        .unwrap(),
+    };
+    send(&mut svm, &[set_rate_limit], &authority, &[&authority]).expect("set rate limit failed");
+
+    let depositor_lamports = svm.get_account(&depositor.pubkey()).unwrap().lamports;
+    send(
+        &mut svm,
+        &[withdraw_own_ix(&depositor.pubkey(), LAMPORTS_PER_SOL)],
+        &depositor,
+        &[&depositor],
+    )
+    .expect("withdraw own failed");
+    assert!(
+        svm.get_account(&depositor.pubkey()).unwrap().lamports
+            > depositor_lamports + LAMPORTS_PER_SOL - LAMPORTS_PER_SOL / 1000
+    );
+
+    let recorded = DepositorReceipt::unpack(&svm.get_account(&receipt).unwrap().data).unwrap();
+    assert_eq!(recorded.total_deposited, 3 * LAMPORTS_PER_SOL / 2);
+    assert_eq!(recorded.balance, LAMPORTS_PER_SOL / 2);
+
+    // refunds count against the rate limit
+    let result = send(
+        &mut svm,
+        &[withdraw_own_ix(&depositor.pubkey(), LAMPORTS_PER_SOL / 2)],
+        &depositor,
+        &[&depositor],
+    );
+    assert_vault_error(result, VaultError::RateLimitExceeded);
+
+    // non-refundable vaults record receipts when asked to but never refund
+    let (other_state_pda, other_vault_pda) =
+        create_native_vault(&mut svm, &program_id, &authority, 1);
+    let other_receipt = receipt_pda(&other_state_pda, &depositor.pubkey());
+
+    let mut deposit = vault_ix(
+        &program_id,
+        &VaultInstruction::DepositWithReceipt {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &depositor.pubkey(),
+        &other_state_pda,
+        &depositor.pubkey(),
+        &other_vault_pda,
+    );
+    deposit
+        .accounts
+        .push(AccountMeta::new(other_receipt, false));
+    send(&mut svm, &[deposit], &depositor, &[&depositor]).expect("deposit failed");
+
+    let recorded =
+        DepositorReceipt::unpack(&svm.get_account(&other_receipt).unwrap().data).unwrap();
+    assert_eq!(recorded.balance, LAMPORTS_PER_SOL);
+
+    let mut ix = withdraw_own_ix(&depositor.pubkey(), 1);
+    ix.accounts[1] = AccountMeta::new(other_state_pda, false);
+    ix.accounts[2] = AccountMeta::new(other_receipt, false);
+    ix.accounts[3] = AccountMeta::new(other_vault_pda, false);
+    let result = send(&mut svm, &[ix], &depositor, &[&depositor]);
+    assert_vault_error(result, VaultError::NotRefundableVault);
+}
+
+#[test]
+pub fn test_vault_stats() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, vault_account_pda) =
+        create_native_vault(&mut svm, &program_id, &authority, 0);
+
+    let created_at = svm.get_sysvar::<Clock>().unix_timestamp;
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(
+        state.stats,
+        VaultStats {
+            created_at,
+            last_activity_ts: created_at,
+            ..VaultStats::default()
+        }
+    );
+
+    let deposit = vault_ix(
+        &program_id,
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    send(
+        &mut svm,
+        std::slice::from_ref(&deposit),
+        &authority,
+        &[&authority],
+    )
+    .expect("deposit failed");
+
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp += 60;
+    svm.set_sysvar(&clock);
+
+    send(&mut svm, &[deposit], &authority, &[&authority]).expect("deposit failed");
+
+    let withdraw = vault_ix(
+        &program_id,
+        &VaultInstruction::Withdraw {
+            amount: LAMPORTS_PER_SOL / 2,
+        },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &vault_account_pda,
+        &authority.pubkey(),
+    );
+    send(&mut svm, &[withdraw], &authority, &[&authority]).expect("withdraw failed");
+
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(
+        state.stats,
+        VaultStats {
+            total_deposited: 2 * LAMPORTS_PER_SOL,
+            total_withdrawn: LAMPORTS_PER_SOL / 2,
+            deposit_count: 2,
+            withdraw_count: 1,
+            created_at,
+            last_activity_ts: created_at + 60,
+        }
+    );
+}
+
+#[test]
+pub fn test_events() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 0);
+    let (vault_account_pda, _) = vault_pda(&program_id, &vault_state_pda);
+    let to_program_key =
+        |key: &Pubkey| solana_program::pubkey::Pubkey::new_from_array(key.to_bytes());
+
+    let initialize = initialize_ix(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &vault_account_pda,
+        0,
+        true,
+    );
+    let meta =
+        send(&mut svm, &[initialize], &authority, &[&authority]).expect("failed to create vault");
+    assert_eq!(
+        decode_events(&to_program_key(&program_id), &meta.logs),
+        vec![VaultEvent::VaultInitialized(VaultInitialized {
+            vault_state: to_program_key(&vault_state_pda),
+            authority: to_program_key(&authority.pubkey()),
+            token_mint: to_program_key(&program::ID),
+            is_native: true,
+            vault_id: 0,
+        })]
+    );
+
+    let deposit = vault_ix(
+        &program_id,
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    let withdraw = vault_ix(
+        &program_id,
+        &VaultInstruction::Withdraw {
+            amount: LAMPORTS_PER_SOL / 2,
+        },
+        &authority.pubkey(),
+        &vault_state_pda,
+        &vault_account_pda,
+        &authority.pubkey(),
+    );
+    let meta = send(&mut svm, &[deposit, withdraw], &authority, &[&authority])
+        .expect("deposit and withdraw failed");
+    assert_eq!(
+        decode_events(&to_program_key(&program_id), &meta.logs),
+        vec![
+            VaultEvent::Deposited(Deposited {
+                vault_state: to_program_key(&vault_state_pda),
+                depositor: to_program_key(&authority.pubkey()),
+                amount: LAMPORTS_PER_SOL,
+                shares: 0,
+            }),
+            VaultEvent::Withdrawn(Withdrawn {
+                vault_state: to_program_key(&vault_state_pda),
+                destination: to_program_key(&authority.pubkey()),
+                amount: LAMPORTS_PER_SOL / 2,
+            }),
+        ]
+    );
+
+    // ordinary messages and foreign data are ignored
+    let program = program_id.to_string();
+    let logs = [
+        format!("Program {program} invoke [1]"),
+        "Program log: Deposited 1 tokens".to_string(),
+        "Program data: aGVsbG8=".to_string(),
+        "Program data: not-base64".to_string(),
+        format!("Program {program} success"),
+    ];
+    assert!(decode_events(&to_program_key(&program_id), &logs).is_empty());
+
+    // a vault event logged by another program, whether called by the vault or
+    // on its own, is not the vault's
+    let event = VaultEvent::Paused(Paused {
+        vault_state: to_program_key(&vault_state_pda),
+        paused_by: to_program_key(&authority.pubkey()),
+    });
+    let mut data = EVENT_PREFIX.to_vec();
+    data.extend(borsh::to_vec(&event).unwrap());
+    let forged = format!("Program data: {}", STANDARD.encode(&data));
+    let other = Pubkey::new_unique().to_string();
+    let logs = [
+        format!("Program {other} invoke [1]"),
+        forged.clone(),
+        format!("Program {other} success"),
+        format!("Program {program} invoke [1]"),
+        format!("Program {other} invoke [2]"),
+        forged.clone(),
+        format!("Program {other} failed: custom program error: 0x0"),
+        forged.clone(),
+        format!("Program {program} success"),
+        forged.clone(),
+    ];
+    assert_eq!(
+        decode_events(&to_program_key(&program_id), &logs),
+        vec![event]
+    );
+
+    // a called program cannot end its own frame by logging `success`
+    let logs = [
+        format!("Program {program} invoke [1]"),
+        format!("Program {other} invoke [2]"),
+        "Program log: success".to_string(),
+        forged.clone(),
+        "Program log: failed: to pretend".to_string(),
+        forged,
+        format!("Program {other} success"),
+        format!("Program {program} success"),
+    ];
+    assert!(decode_events(&to_program_key(&program_id), &logs).is_empty());
+}
+
+#[test]
+pub fn test_vault_token_2022() {
+    let (mut svm, program_id, authority) = setup();
+    let token_program = token_2022_program_id();
+
+    let (vault_state_pda, vault_token_account, token_mint, mint_authority) =
+        create_spl_vault(&mut svm, &program_id, &authority, 0, &token_program);
+    let authority_token_account = fund_token_account(
+        &mut svm,
+        &token_mint,
+        &mint_authority,
+        &authority.pubkey(),
+        LAMPORTS_PER_SOL,
+        &token_program,
+    );
+
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(
+        state.token_program,
+        solana_program::pubkey::Pubkey::new_from_array(token_program.to_bytes())
+    );
+    assert_eq!(state.decimals, 9);
+
+    // Token-2022 ATAs carry the immutable owner extension, so they only
+    // decode with extension-aware unpacking
+    let vault_token = svm.get_account(&vault_token_account).unwrap();
+    assert_eq!(vault_token.owner, token_program);
+    assert!(vault_token.data.len() > spl_token::state::Account::LEN);
+
+    let deposit = with_token_mint(
+        vault_ix(
+            &program_id,
+            &VaultInstruction::Deposit {
+                amount: LAMPORTS_PER_SOL,
+            },
+            &authority.pubkey(),
+            &vault_state_pda,
+            &authority_token_account,
+            &vault_token_account,
+        ),
+        &token_program,
+        &token_mint,
+    );
+    send(&mut svm, &[deposit], &authority, &[&authority]).expect("deposit failed");
+    assert_eq!(token_balance(&svm, &vault_token_account), LAMPORTS_PER_SOL);
+    assert_eq!(token_balance(&svm, &authority_token_account), 0);
+
+    let withdraw = |token_program: &Pubkey, amount: u64| {
+        with_token_mint(
+            vault_ix(
+                &program_id,
+                &VaultInstruction::Withdraw { amount },
+                &authority.pubkey(),
+                &vault_state_pda,
+                &vault_token_account,
+                &authority_token_account,
+            ),
+            token_program,
+            &token_mint,
+        )
+    };
+
+    // the vault only accepts the token program it was created with
+    let result = send(
+        &mut svm,
+        &[withdraw(&token_program_id(), LAMPORTS_PER_SOL)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::InvalidTokenProgram);
+
+    send(
+        &mut svm,
+        &[withdraw(&token_program, LAMPORTS_PER_SOL / 4)],
+        &authority,
+        &[&authority],
+    )
+    .expect("withdraw failed");
+    assert_eq!(
+        token_balance(&svm, &vault_token_account),
+        LAMPORTS_PER_SOL * 3 / 4
+    );
+    assert_eq!(
+        token_balance(&svm, &authority_token_account),
+        LAMPORTS_PER_SOL / 4
+    );
+}
+
+#[test]
+pub fn test_token_2022_extensions() {
+    let (mut svm, program_id, authority) = setup();
+    let token_program = token_2022_program_id();
+    let mint_authority = Keypair::new();
+    svm.airdrop(&mint_authority.pubkey(), LAMPORTS_PER_SOL)
+        .unwrap();
+
+    // 1% transfer fee, uncapped
+    let fee_mint = create_mint_with_extensions(
+        &mut svm,
+        &mint_authority,
+        &[ExtensionType::TransferFeeConfig],
+        |mint| {
+            vec![initialize_transfer_fee_config(
+                &spl_token_2022::id(),
+                mint,
+                None,
+                None,
+                100,
+                u64::MAX,
+            )
+            .unwrap()]
+        },
+    );
+
+    let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 0);
+    let vault_token_account = ata(&vault_state_pda, &fee_mint, &token_program);
+    let mut initialize = initialize_ix(
+        &program_id,
+        &authority.pubkey(),
+        &fee_mint,
+        &vault_token_account,
+        0,
+        false,
+    );
+    initialize.accounts[4].pubkey = token_program;
+    send(&mut svm, &[initialize], &authority, &[&authority]).expect("failed to create vault");
+
+    let authority_token_account = fund_token_account(
+        &mut svm,
+        &fee_mint,
+        &mint_authority,
+        &authority.pubkey(),
+        10_000,
+        &token_program,
+    );
+
+    let deposit = with_token_mint(
+        vault_ix(
+            &program_id,
+            &VaultInstruction::Deposit { amount: 10_000 },
+            &authority.pubkey(),
+            &vault_state_pda,
+            &authority_token_account,
+            &vault_token_account,
+        ),
+        &token_program,
+        &fee_mint,
+    );
+    let meta = send(&mut svm, &[deposit], &authority, &[&authority]).expect("deposit failed");
+
+    // the fee is withheld in the vault's account, so only 9,900 is credited
+    assert_eq!(token_balance(&svm, &vault_token_account), 9_900);
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.stats.total_deposited, 9_900);
+    assert!(matches!(
+        decode_events(
+            &solana_program::pubkey::Pubkey::new_from_array(program_id.to_bytes()),
+            &meta.logs,
+        )
+        .as_slice(),
+        [VaultEvent::Deposited(Deposited { amount: 9_900, .. })]
+    ));
+
+    // a permanent delegate could drain the vault, so it needs an explicit opt-in
+    let delegate_mint = create_mint_with_extensions(
+        &mut svm,
+        &mint_authority,
+        &[ExtensionType::PermanentDelegate],
+        |mint| {
+            vec![initialize_permanent_delegate(
+                &spl_token_2022::id(),
+                mint,
+                &solana_program::pubkey::Pubkey::new_unique(),
+            )
+            .unwrap()]
+        },
+    );
+
+    let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 1);
+    let initialize = |config: VaultConfig| {
+        let mut ix = initialize_ix_with_config(
+            &program_id,
+            &authority.pubkey(),
+            &delegate_mint,
+            &ata(&vault_state_pda, &delegate_mint, &token_program),
+            1,
+            false,
+            config,
+        );
+        ix.accounts[4].pubkey = token_program;
+        ix
+    };
+
+    let result = send(
+        &mut svm,
+        &[initialize(VaultConfig::default())],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::UnsupportedMintExtension);
+
+    send(
+        &mut svm,
+        &[initialize(VaultConfig {
+            allow_unsafe_extensions: true,
+            ..VaultConfig::default()
+        })],
+        &authority,
+        &[&authority],
+    )
+    .expect("opted-in vault should accept the mint");
+}
+
+#[test]
+pub fn test_multi_asset() {
+    let (mut svm, program_id, authority) = setup();
+    let (vault_state_pda, vault_account_pda) =
+        create_native_vault(&mut svm, &program_id, &authority, 0);
+
+    let mint_authority = Keypair::new();
+    svm.airdrop(&mint_authority.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop mint auth");
+    let token_program = token_2022_program_id();
+    let asset_mint = create_mint(&mut svm, &mint_authority, &token_program);
+    let authority_token_account = fund_token_account(
+        &mut svm,
+        &asset_mint,
+        &mint_authority,
+        &authority.pubkey(),
+        1_000,
+        &token_program,
+    );
+
+    let (asset_pda, _) = Pubkey::find_program_address(
+        &[b"asset", vault_state_pda.as_ref(), asset_mint.as_ref()],
+        &program_id,
+    );
+    let asset_token_account = ata(&vault_state_pda, &asset_mint, &token_program);
+    let mint_arg = solana_program::pubkey::Pubkey::new_from_array(asset_mint.to_bytes());
+
+    let add_asset = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(asset_pda, false),
+            AccountMeta::new_readonly(asset_mint, false),
+            AccountMeta::new(asset_token_account, false),
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new_readonly(associated_token_program_id(), false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::AddAsset { mint: mint_arg }).unwrap(),
+    };
+    send(
+        &mut svm,
+        std::slice::from_ref(&add_asset),
+        &authority,
+        &[&authority],
+    )
+    .expect("add asset failed");
+
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.asset_count, 1);
+
+    // a mint can only be registered once
+    let result = send(&mut svm, &[add_asset], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::AssetExists);
+
+    // the asset's mint and slot follow the system program
+    let asset_ix = |instruction: &VaultInstruction, source: &Pubkey, destination: &Pubkey| {
+        let mut ix = vault_ix(
+            &program_id,
+            instruction,
+            &authority.pubkey(),
+            &vault_state_pda,
+            source,
+            destination,
+        );
+        ix.accounts[4].pubkey = token_program;
+        ix.accounts
+            .push(AccountMeta::new_readonly(asset_mint, false));
+        ix.accounts.push(AccountMeta::new(asset_pda, false));
+        ix
+    };
+
+    let deposit = asset_ix(
+        &VaultInstruction::Deposit { amount: 1_000 },
+        &authority_token_account,
+        &asset_token_account,
+    );
+    send(&mut svm, &[deposit], &authority, &[&authority]).expect("asset deposit failed");
+    assert_eq!(token_balance(&svm, &asset_token_account), 1_000);
+
+    let withdraw = |amount: u64| {
+        asset_ix(
+            &VaultInstruction::Withdraw { amount },
+            &asset_token_account,
+            &authority_token_account,
+        )
+    };
+    send(&mut svm, &[withdraw(400)], &authority, &[&authority]).expect("asset withdraw failed");
+    assert_eq!(token_balance(&svm, &asset_token_account), 600);
+    assert_eq!(token_balance(&svm, &authority_token_account), 400);
+
+    let asset = VaultAsset::unpack(&svm.get_account(&asset_pda).unwrap().data).unwrap();
+    assert_eq!(asset.total_deposited, 1_000);
+    assert_eq!(asset.total_withdrawn, 400);
+    assert_eq!(asset.decimals, 9);
+
+    // assets leave the vault's own stats untouched
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.stats.deposit_count, 0);
+    assert_eq!(state.stats.withdraw_count, 0);
+
+    // the rate limit cannot meter other mints, so it holds assets in place
+    let set_rate_limit_ix = |rate_limit: RateLimit| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::SetRateLimit { rate_limit }).unwrap(),
+    };
+    send(
+        &mut svm,
+        &[set_rate_limit_ix(RateLimit {
+            period: RateLimitPeriod::Epoch,
+            max_amount: LAMPORTS_PER_SOL,
+            window_seconds: 0,
+        })],
+        &authority,
+        &[&authority],
+    )
+    .expect("set rate limit failed");
+
+    let result = send(&mut svm, &[withdraw(600)], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::AssetRateLimited);
+
+    send(
+        &mut svm,
+        &[set_rate_limit_ix(RateLimit::default())],
+        &authority,
+        &[&authority],
+    )
+    .expect("queue rate limit removal failed");
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp += RATE_LIMIT_CHANGE_DELAY;
+    svm.set_sysvar(&clock);
+
+    let close = vault_ix(
+        &program_id,
+        &VaultInstruction::Close,
+        &authority.pubkey(),
+        &vault_state_pda,
+        &vault_account_pda,
+        &authority.pubkey(),
+    );
+    let result = send(
+        &mut svm,
+        std::slice::from_ref(&close),
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::VaultHasAssets);
+
+    let remove_asset = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(asset_pda, false),
+            AccountMeta::new(asset_token_account, false),
+            AccountMeta::new(authority.pubkey(), false),
+            AccountMeta::new_readonly(token_program, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::RemoveAsset { mint: mint_arg }).unwrap(),
+    };
+    let result = send(
+        &mut svm,
+        std::slice::from_ref(&remove_asset),
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::VaultNotEmpty);
+
+    send(&mut svm, &[withdraw(600)], &authority, &[&authority]).expect("asset withdraw failed");
+    send(&mut svm, &[remove_asset], &authority, &[&authority]).expect("remove asset failed");
+
+    assert!(svm
+        .get_account(&asset_pda)
+        .is_none_or(|account| account.data.is_empty()));
+    assert!(svm
+        .get_account(&asset_token_account)
+        .is_none_or(|account| account.data.is_empty()));
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.asset_count, 0);
+
+    send(&mut svm, &[close], &authority, &[&authority]).expect("close failed");
+}
+
+#[test]
+pub fn test_wrap_native() {
+    let (mut svm, program_id, authority) = setup();
+
+    // litesvm ships SPL Token without its native mint account
+    let native_mint = Pubkey::new_from_array(spl_token::native_mint::id().to_bytes());
+    let mut mint_data = vec![0; Mint::LEN];
+    Mint {
+        decimals: spl_token::native_mint::DECIMALS,
+        is_initialized: true,
+        ..Mint::default()
+    }
+    .pack_into_slice(&mut mint_data);
+    svm.set_account(
+        native_mint,
+        Account {
+            lamports: Rent::default().minimum_balance(Mint::LEN),
+            data: mint_data,
+            owner: token_program_id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+
+    let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 0);
+    let vault_token_account = ata(&vault_state_pda, &native_mint, &token_program_id());
+
+    // only SPL vaults for the native mint can wrap
+    let init = |token_mint: &Pubkey, is_native: bool| {
+        initialize_ix_with_config(
+            &program_id,
+            &authority.pubkey(),
+            token_mint,
+            &vault_token_account,
+            0,
+            is_native,
+            VaultConfig {
+                wrap_native: true,
+                ..VaultConfig::default()
+            },
+        )
+    };
+    let result = send(
+        &mut svm,
+        &[init(&native_mint, true)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::InvalidVaultConfig);
+
+    send(
+        &mut svm,
+        &[init(&native_mint, false)],
+        &authority,
+        &[&authority],
+    )
+    .expect("failed to initialize vault");
+
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert!(state.wrap_native);
+
+    // the deposit is taken in lamports; the source account is unused
+    let deposit = with_token_mint(
+        vault_ix(
+            &program_id,
+            &VaultInstruction::Deposit {
+                amount: LAMPORTS_PER_SOL,
+            },
+            &authority.pubkey(),
+            &vault_state_pda,
+            &authority.pubkey(),
+            &vault_token_account,
+        ),
+        &token_program_id(),
+        &native_mint,
+    );
+    send(&mut svm, &[deposit], &authority, &[&authority]).expect("deposit failed");
+    assert_eq!(token_balance(&svm, &vault_token_account), LAMPORTS_PER_SOL);
+
+    let (unwrap_pda, _) =
+        Pubkey::find_program_address(&[b"unwrap", vault_state_pda.as_ref()], &program_id);
+    let payer = Keypair::new();
+    svm.airdrop(&payer.pubkey(), LAMPORTS_PER_SOL)
+        .expect("failed to airdrop payer");
+
+    let unwrap = |amount: u64, destination: &Pubkey| {
+        let mut ix = with_token_mint(
+            vault_ix(
+                &program_id,
+                &VaultInstruction::Withdraw { amount },
+                &authority.pubkey(),
+                &vault_state_pda,
+                &vault_token_account,
+                destination,
+            ),
+            &token_program_id(),
+            &native_mint,
+        );
+        ix.accounts.push(AccountMeta::new(unwrap_pda, false));
+        ix.accounts.push(AccountMeta::new(payer.pubkey(), true));
+        ix
+    };
+
+    // lamports only go to the authority's own wallet
+    let stranger = Pubkey::new_unique();
+    let result = send(
+        &mut svm,
+        &[unwrap(LAMPORTS_PER_SOL / 4, &stranger)],
+        &payer,
+        &[&payer, &authority],
+    );
+    assert_vault_error(result, VaultError::InvalidTokenAccountOwner);
+
+    let authority_lamports = svm.get_account(&authority.pubkey()).unwrap().lamports;
+    let payer_lamports = svm.get_account(&payer.pubkey()).unwrap().lamports;
+    let state_lamports = svm.get_account(&vault_state_pda).unwrap().lamports;
+
+    // the payer pays the fee, so the authority gains exactly the amount
+    send(
+        &mut svm,
+        &[unwrap(LAMPORTS_PER_SOL / 4, &authority.pubkey())],
+        &payer,
+        &[&payer, &authority],
+    )
+    .expect("unwrapping withdraw failed");
+    assert_eq!(
+        svm.get_account(&authority.pubkey()).unwrap().lamports,
+        authority_lamports + LAMPORTS_PER_SOL / 4
+    );
+    assert_eq!(
+        token_balance(&svm, &vault_token_account),
+        LAMPORTS_PER_SOL * 3 / 4
+    );
+
+    // the temporary account's rent goes back to the payer
+    assert!(
+        svm.get_account(&payer.pubkey()).unwrap().lamports
+            > payer_lamports - LAMPORTS_PER_SOL / 1000
+    );
+    assert_eq!(
+        svm.get_account(&vault_state_pda).unwrap().lamports,
+        state_lamports
+    );
+    assert!(svm
+        .get_account(&unwrap_pda)
+        .is_none_or(|account| account.lamports == 0));
+
+    // a token account destination still receives wSOL
+    let authority_token_account = ata(&authority.pubkey(), &native_mint, &token_program_id());
+    send(
+        &mut svm,
+        &[create_ata_ix(
+            &authority.pubkey(),
+            &authority.pubkey(),
+            &native_mint,
+            &token_program_id(),
+        )],
+        &authority,
+        &[&authority],
+    )
+    .expect("failed to create wSOL account");
+
+    let withdraw = with_token_mint(
+        vault_ix(
+            &program_id,
+            &VaultInstruction::Withdraw {
+                amount: LAMPORTS_PER_SOL / 4,
+            },
+            &authority.pubkey(),
+            &vault_state_pda,
+            &vault_token_account,
+            &authority_token_account,
+        ),
+        &token_program_id(),
+        &native_mint,
+    );
+    send(&mut svm, &[withdraw], &authority, &[&authority]).expect("withdraw failed");
+    assert_eq!(
+        token_balance(&svm, &authority_token_account),
+        LAMPORTS_PER_SOL / 4
+    );
+}
+
+#[test]
+pub fn test_native_rent_reserve() {
+    let (mut svm, program_id, authority) = setup();
+    let (vault_state_pda, vault_account_pda) =
+        create_native_vault(&mut svm, &program_id, &authority, 0);
+    let rent_reserve = Rent::default().minimum_balance(0);
+
+    let native_ix = |instruction: &VaultInstruction, source: &Pubkey, destination: &Pubkey| {
+        vault_ix(
+            &program_id,
+            instruction,
+            &authority.pubkey(),
+            &vault_state_pda,
+            source,
+            destination,
+        )
+    };
+
+    let deposit = native_ix(
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+    send(&mut svm, &[deposit], &authority, &[&authority]).expect("deposit failed");
+    assert_eq!(
+        svm.get_account(&vault_account_pda).unwrap().lamports,
+        LAMPORTS_PER_SOL + rent_reserve
+    );
+
+    // the rent reserve cannot be withdrawn
+    let overdraw = native_ix(
+        &VaultInstruction::Withdraw {
+            amount: LAMPORTS_PER_SOL + 1,
+        },
+        &vault_account_pda,
+        &authority.pubkey(),
+    );
+    let result = send(&mut svm, &[overdraw], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InsufficientVaultBalance);
+
+    let recipient = Pubkey::new_unique();
+    let withdraw_all = native_ix(
+        &VaultInstruction::WithdrawAll,
+        &vault_account_pda,
+        &recipient,
+    );
+    send(&mut svm, &[withdraw_all], &authority, &[&authority]).expect("withdraw all failed");
+    assert_eq!(
+        svm.get_account(&recipient).unwrap().lamports,
+        LAMPORTS_PER_SOL
+    );
+    assert_eq!(
+        svm.get_account(&vault_account_pda).unwrap().lamports,
+        rent_reserve
+    );
+
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.stats.total_withdrawn, LAMPORTS_PER_SOL);
+
+    // an empty vault has nothing to withdraw
+    let withdraw_all = native_ix(
+        &VaultInstruction::WithdrawAll,
+        &vault_account_pda,
+        &authority.pubkey(),
+    );
+    let result = send(&mut svm, &[withdraw_all], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InsufficientVaultBalance);
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.stats.withdraw_count, 1);
+
+    let withdraw = native_ix(
+        &VaultInstruction::Withdraw { amount: 1 },
+        &vault_account_pda,
+        &authority.pubkey(),
+    );
+    let result = send(&mut svm, &[withdraw], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InsufficientVaultBalance);
+}
+
+#[test]
+pub fn test_close_teardown() {
+    let (mut svm, program_id, authority) = setup();
+    let (vault_state_pda, vault_account_pda) =
+        create_native_vault(&mut svm, &program_id, &authority, 0);
+
+    let native_ix = |instruction: &VaultInstruction, source: &Pubkey, destination: &Pubkey| {
+        vault_ix(
+            &program_id,
+            instruction,
+            &authority.pubkey(),
+            &vault_state_pda,
+            source,
+            destination,
+        )
+    };
+    let close = native_ix(
+        &VaultInstruction::Close,
+        &vault_account_pda,
+        &authority.pubkey(),
+    );
+    let deposit = native_ix(
+        &VaultInstruction::Deposit {
+            amount: LAMPORTS_PER_SOL,
+        },
+        &authority.pubkey(),
+        &vault_account_pda,
+    );
+
+    // the closed state cannot be used again later in the same transaction
+    let err = send(
+        &mut svm,
+        &[close.clone(), deposit],
+        &authority,
+        &[&authority],
+    )
+    .expect_err("deposit into a closed vault should fail")
+    .err;
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(VaultError::InvalidStateData as u32)
+        )
+    );
+    // ...and the failed transaction left the vault untouched
+    let state_account = svm.get_account(&vault_state_pda).unwrap();
+    assert_eq!(state_account.owner, program_id);
+    VaultState::unpack(&state_account.data).expect("vault should still be live");
+
+    send(&mut svm, &[close], &authority, &[&authority]).expect("close failed");
+
+    // only a tombstone holding the generation is left
+    let account = svm.get_account(&vault_state_pda).unwrap();
+    assert_eq!(account.owner, program_id);
+    assert_eq!(
+        account.lamports,
+        Rent::default().minimum_balance(ClosedVault::LEN)
+    );
+    assert!(VaultState::unpack(&account.data).is_err());
+    let closed = ClosedVault::unpack(&account.data).unwrap();
+    assert_eq!(closed.account_type, AccountType::ClosedVault);
+    assert_eq!(closed.generation, 0);
+}
+
+#[test]
+pub fn test_close_sweep() {
+    let (mut svm, program_id, authority) = setup();
+    svm.airdrop(&authority.pubkey(), 5 * LAMPORTS_PER_SOL)
+        .unwrap();
+
+    let native_ix = |instruction: &VaultInstruction, vault_id: u64| {
+        let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), vault_id);
+        let (vault_account_pda, _) = vault_pda(&program_id, &vault_state_pda);
+        let (source, destination) = match instruction {
+            VaultInstruction::Close => (vault_account_pda, authority.pubkey()),
+            _ => (authority.pubkey(), vault_account_pda),
+        };
+        vault_ix(
+            &program_id,
+            instruction,
+            &authority.pubkey(),
+            &vault_state_pda,
+            &source,
+            &destination,
+        )
+    };
+    let deposit = |vault_id: u64| {
+        native_ix(
+            &VaultInstruction::Deposit {
+                amount: LAMPORTS_PER_SOL / 2,
+            },
+            vault_id,
+        )
+    };
+    let admin_ix = |vault_state: &Pubkey, instruction: VaultInstruction| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(*vault_state, false),
+        ],
+        data: borsh::to_vec(&instruction).unwrap(),
+    };
+    let initialize = |vault_id: u64, config: VaultConfig| {
+        let (vault_state_pda, _) = state_pda(&program_id, &authority.pubkey(), vault_id);
+        let (vault_account_pda, _) = vault_pda(&program_id, &vault_state_pda);
+        initialize_ix_with_config(
+            &program_id,
+            &authority.pubkey(),
+            &program::ID,
+            &vault_account_pda,
+            vault_id,
+            true,
+            config,
+        )
+    };
+
+    // a paused vault keeps its funds
+    let (vault_state_pda, _) = create_native_vault(&mut svm, &program_id, &authority, 0);
+    send(&mut svm, &[deposit(0)], &authority, &[&authority]).expect("deposit failed");
+    send(
+        &mut svm,
+        &[admin_ix(&vault_state_pda, VaultInstruction::Pause)],
+        &authority,
+        &[&authority],
+    )
+    .expect("pause failed");
+    let result = send(
+        &mut svm,
+        &[native_ix(&VaultInstruction::Close, 0)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::VaultPaused);
+    send(
+        &mut svm,
+        &[admin_ix(&vault_state_pda, VaultInstruction::Unpause)],
+        &authority,
+        &[&authority],
+    )
+    .expect("unpause failed");
+
+    // the sweep only pays out to an allowlisted authority
+    let (allowlist_pda, _) = Pubkey::find_program_address(
+        &[b"allowlist", vault_state_pda.as_ref(), &0u64.to_le_bytes()],
+        &program_id,
+    );
+    let add_ix = |destination: &Pubkey| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(allowlist_pda, false),
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::AddAllowedDestination {
+            destination: solana_program::pubkey::Pubkey::new_from_array(destination.to_bytes()),
+        })
+        .unwrap(),
+    };
+    send(
+        &mut svm,
+        &[add_ix(&Pubkey::new_unique())],
+        &authority,
+        &[&authority],
+    )
+    .expect("add destination failed");
+    let mut close = native_ix(&VaultInstruction::Close, 0);
+    close
+        .accounts
+        .push(AccountMeta::new_readonly(allowlist_pda, false));
+    let result = send(
+        &mut svm,
+        std::slice::from_ref(&close),
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::DestinationNotAllowed);
+
+    send(
+        &mut svm,
+        &[add_ix(&authority.pubkey())],
+        &authority,
+        &[&authority],
+    )
+    .expect("add authority failed");
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp += ALLOWLIST_ADDITION_DELAY;
+    svm.set_sysvar(&clock);
+    send(&mut svm, &[close], &authority, &[&authority]).expect("close failed");
+
+    // a timelocked vault's funds leave through RequestWithdraw
+    send(
+        &mut svm,
+        &[initialize(
+            1,
+            VaultConfig {
+                withdraw_delay: 3600,
+                ..VaultConfig::default()
+            },
+        )],
+        &authority,
+        &[&authority],
+    )
+    .expect("failed to create timelocked vault");
+    send(&mut svm, &[deposit(1)], &authority, &[&authority]).expect("deposit failed");
+    let result = send(
+        &mut svm,
+        &[native_ix(&VaultInstruction::Close, 1)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::WithdrawTimelocked);
+
+    // receipts are owed back to depositors
+    let (refundable_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 2);
+    let (refundable_vault_pda, _) = vault_pda(&program_id, &refundable_state_pda);
+    send(
+        &mut svm,
+        &[initialize(
+            2,
+            VaultConfig {
+                refundable: true,
+                ..VaultConfig::default()
+            },
+        )],
+        &authority,
+        &[&authority],
+    )
+    .expect("failed to create refundable vault");
+    let (receipt_pda, _) = Pubkey::find_program_address(
+        &[
+            b"receipt",
+            refundable_state_pda.as_ref(),
+            &0u64.to_le_bytes(),
+            authority.pubkey().as_ref(),
+        ],
+        &program_id,
+    );
+    let mut deposit_with_receipt = native_ix(
+        &VaultInstruction::DepositWithReceipt {
+            amount: LAMPORTS_PER_SOL / 2,
+        },
+        2,
+    );
+    deposit_with_receipt
+        .accounts
+        .push(AccountMeta::new(receipt_pda, false));
+    send(&mut svm, &[deposit_with_receipt], &authority, &[&authority])
+        .expect("deposit with receipt failed");
+    let state = VaultState::unpack(&svm.get_account(&refundable_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.outstanding_receipts, LAMPORTS_PER_SOL / 2);
+
+    let result = send(
+        &mut svm,
+        &[native_ix(&VaultInstruction::Close, 2)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::OutstandingClaims);
+
+    let withdraw_own = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(refundable_state_pda, false),
+            AccountMeta::new(receipt_pda, false),
+            AccountMeta::new(refundable_vault_pda, false),
+            AccountMeta::new(authority.pubkey(), false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::WithdrawOwn {
+            amount: LAMPORTS_PER_SOL / 2,
+        })
+        .unwrap(),
+    };
+    send(&mut svm, &[withdraw_own], &authority, &[&authority]).expect("withdraw own failed");
+    let state = VaultState::unpack(&svm.get_account(&refundable_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.outstanding_receipts, 0);
+    send(
+        &mut svm,
+        &[native_ix(&VaultInstruction::Close, 2)],
+        &authority,
+        &[&authority],
+    )
+    .expect("close refunded vault failed");
+
+    // shares are owed back to their holders
+    let (pooled_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 3);
+    let (pooled_vault_pda, _) = vault_pda(&program_id, &pooled_state_pda);
+    let (share_mint, _) = Pubkey::find_program_address(
+        &[b"shares", pooled_state_pda.as_ref(), &0u64.to_le_bytes()],
+        &program_id,
+    );
+    let share_account = ata(&authority.pubkey(), &share_mint, &token_program_id());
+    let mut initialize_pooled = initialize(
+        3,
+        VaultConfig {
+            pooled: true,
+            ..VaultConfig::default()
+        },
+    );
+    initialize_pooled
+        .accounts
+        .push(AccountMeta::new(share_mint, false));
+    send(&mut svm, &[initialize_pooled], &authority, &[&authority])
+        .expect("failed to create pooled vault");
+    send(
+        &mut svm,
+        &[create_ata_ix(
+            &authority.pubkey(),
+            &authority.pubkey(),
+            &share_mint,
+            &token_program_id(),
+        )],
+        &authority,
+        &[&authority],
+    )
+    .expect("failed to create share account");
+    let mut pooled_deposit = deposit(3);
+    pooled_deposit
+        .accounts
+        .push(AccountMeta::new(share_mint, false));
+    pooled_deposit
+        .accounts
+        .push(AccountMeta::new(share_account, false));
+    send(&mut svm, &[pooled_deposit], &authority, &[&authority]).expect("pooled deposit failed");
+
+    let mut close_pooled = native_ix(&VaultInstruction::Close, 3);
+    close_pooled
+        .accounts
+        .push(AccountMeta::new_readonly(share_mint, false));
+    let result = send(
+        &mut svm,
+        std::slice::from_ref(&close_pooled),
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::OutstandingClaims);
+
+    let shares = spl_token::state::Account::unpack(&svm.get_account(&share_account).unwrap().data)
+        .unwrap()
+        .amount;
+    let redeem = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(pooled_state_pda, false),
+            AccountMeta::new(share_mint, false),
+            AccountMeta::new(share_account, false),
+            AccountMeta::new(pooled_vault_pda, false),
+            AccountMeta::new(authority.pubkey(), false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::Redeem { shares }).unwrap(),
+    };
+    send(&mut svm, &[redeem], &authority, &[&authority]).expect("redeem failed");
+    send(&mut svm, &[close_pooled], &authority, &[&authority]).expect("close pooled vault failed");
+}
+
+#[test]
+pub fn test_reopen_vault() {
+    let (mut svm, program_id, authority) = setup();
+
+    let (vault_state_pda, vault_token_account, token_mint, mint_authority) =
+        create_spl_vault(&mut svm, &program_id, &authority, 0, &token_program_id());
+    let authority_token_account = fund_token_account(
+        &mut svm,
+        &token_mint,
+        &mint_authority,
+        &authority.pubkey(),
+        0,
+        &token_program_id(),
+    );
+
+    let reopen_ix = |token_mint: &Pubkey| {
+        initialize_ix(
+            &program_id,
+            &authority.pubkey(),
+            token_mint,
+            &ata(&vault_state_pda, token_mint, &token_program_id()),
+            0,
+            false,
+        )
+    };
+
+    // a live vault cannot be initialized again
+    let result = send(
+        &mut svm,
+        &[reopen_ix(&token_mint)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::AlreadyInitialized);
+
+    let close = with_token_mint(
+        vault_ix(
+            &program_id,
+            &VaultInstruction::Close,
+            &authority.pubkey(),
+            &vault_state_pda,
+            &vault_token_account,
+            &authority_token_account,
+        ),
+        &token_program_id(),
+        &token_mint,
+    );
+    send(&mut svm, &[close], &authority, &[&authority]).expect("close failed");
+
+    // lamports sent to the closed address must not block reopening it
+    send(
+        &mut svm,
+        &[solana_system_interface::instruction::transfer(
+            &authority.pubkey(),
+            &vault_state_pda,
+            1,
+        )],
+        &authority,
+        &[&authority],
+    )
+    .expect("transfer failed");
+
+    // the new mint is validated like any other
+    let result = send(
+        &mut svm,
+        &[reopen_ix(&Pubkey::new_unique())],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::MintMismatch);
+
+    let new_mint = create_mint(&mut svm, &mint_authority, &token_program_id());
+    let new_vault_token_account = ata(&vault_state_pda, &new_mint, &token_program_id());
+    send(&mut svm, &[reopen_ix(&new_mint)], &authority, &[&authority]).expect("reopen failed");
+
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.generation, 1);
+    assert_eq!(
+        state.token_mint,
+        solana_program::pubkey::Pubkey::new_from_array(new_mint.to_bytes())
+    );
+    assert_eq!(
+        state.token_account,
+        solana_program::pubkey::Pubkey::new_from_array(new_vault_token_account.to_bytes())
+    );
+    assert_eq!(state.stats.total_deposited, 0);
+    assert_eq!(token_balance(&svm, &new_vault_token_account), 0);
+
+    let authority_new_token_account = fund_token_account(
+        &mut svm,
+        &new_mint,
+        &mint_authority,
+        &authority.pubkey(),
+        500,
+        &token_program_id(),
+    );
+    let deposit = with_token_mint(
+        vault_ix(
+            &program_id,
+            &VaultInstruction::Deposit { amount: 500 },
+            &authority.pubkey(),
+            &vault_state_pda,
+            &authority_new_token_account,
+            &new_vault_token_account,
+        ),
+        &token_program_id(),
+        &new_mint,
+    );
+    send(&mut svm, &[deposit], &authority, &[&authority]).expect("deposit failed");
+    assert_eq!(token_balance(&svm, &new_vault_token_account), 500);
+}
+
+#[test]
+pub fn test_reopen_derived_accounts() {
+    let (mut svm, program_id, authority) = setup();
+    let (vault_state_pda, vault_account_pda) =
+        create_native_vault(&mut svm, &program_id, &authority, 0);
+
+    let bot = Keypair::new();
+    svm.airdrop(&bot.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let to_program_key =
+        |key: &Pubkey| solana_program::pubkey::Pubkey::new_from_array(key.to_bytes());
+    let derived_pda = |prefix: &[u8], generation: u64, key: &[u8]| {
+        Pubkey::find_program_address(
+            &[
+                prefix,
+                vault_state_pda.as_ref(),
+                &generation.to_le_bytes(),
+                key,
+            ],
+            &program_id,
+        )
+        .0
+    };
+
+    let deposit_with_receipt_ix = |generation: u64| {
+        let mut ix = vault_ix(
+            &program_id,
+            &VaultInstruction::DepositWithReceipt {
+                amount: LAMPORTS_PER_SOL / 2,
+            },
+            &authority.pubkey(),
+            &vault_state_pda,
+            &authority.pubkey(),
+            &vault_account_pda,
+        );
+        ix.accounts.push(AccountMeta::new(
+            derived_pda(b"receipt", generation, authority.pubkey().as_ref()),
+            false,
+        ));
+        ix
+    };
+    let approve_ix = |generation: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new_readonly(vault_state_pda, false),
+            AccountMeta::new(
+                derived_pda(b"delegate", generation, bot.pubkey().as_ref()),
+                false,
+            ),
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::ApproveDelegate {
+            delegate: to_program_key(&bot.pubkey()),
+            allowance: LAMPORTS_PER_SOL,
+            expires_at: 0,
+        })
+        .unwrap(),
+    };
+    let delegated_withdraw_ix = |generation: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(bot.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(
+                derived_pda(b"delegate", generation, bot.pubkey().as_ref()),
+                false,
+            ),
+            AccountMeta::new(vault_account_pda, false),
+            AccountMeta::new(bot.pubkey(), false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::DelegatedWithdraw {
+            amount: LAMPORTS_PER_SOL / 4,
+        })
+        .unwrap(),
+    };
+    let add_destination_ix = |generation: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(vault_state_pda, false),
+            AccountMeta::new(derived_pda(b"allowlist", generation, &[]), false),
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::AddAllowedDestination {
+            destination: to_program_key(&authority.pubkey()),
+        })
+        .unwrap(),
+    };
+
+    send(
+        &mut svm,
+        &[
+            deposit_with_receipt_ix(0),
+            approve_ix(0),
+            add_destination_ix(0),
+        ],
+        &authority,
+        &[&authority],
+    )
+    .expect("failed to set up derived accounts");
+
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp += ALLOWLIST_ADDITION_DELAY;
+    svm.set_sysvar(&clock);
+
+    let mut close = vault_ix(
+        &program_id,
+        &VaultInstruction::Close,
+        &authority.pubkey(),
+        &vault_state_pda,
+        &vault_account_pda,
+        &authority.pubkey(),
+    );
+    close.accounts.push(AccountMeta::new_readonly(
+        derived_pda(b"allowlist", 0, &[]),
+        false,
+    ));
+    send(&mut svm, &[close], &authority, &[&authority]).expect("close failed");
+
+    // the reopened vault is the next generation, with no allowlist yet
+    create_native_vault(&mut svm, &program_id, &authority, 0);
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert_eq!(state.generation, 1);
+    assert!(!state.allowlist_enabled);
+
+    // accounts of the closed vault do not verify against the new one
+    let result = send(
+        &mut svm,
+        &[deposit_with_receipt_ix(0)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::InvalidReceipt);
+    send(
+        &mut svm,
+        &[deposit_with_receipt_ix(1)],
+        &authority,
+        &[&authority],
+    )
+    .expect("deposit with new receipt failed");
+
+    let result = send(&mut svm, &[delegated_withdraw_ix(0)], &bot, &[&bot]);
+    assert_vault_error(result, VaultError::InvalidDelegate);
+
+    let result = send(
+        &mut svm,
+        &[add_destination_ix(0)],
+        &authority,
+        &[&authority],
+    );
+    assert_vault_error(result, VaultError::InvalidAllowlist);
+
+    // the allowlist can be enabled again under the new generation
+    send(
+        &mut svm,
+        &[add_destination_ix(1)],
+        &authority,
+        &[&authority],
+    )
+    .expect("add destination failed");
+    let state = VaultState::unpack(&svm.get_account(&vault_state_pda).unwrap().data).unwrap();
+    assert!(state.allowlist_enabled);
+
+    // a reopened multisig vault numbers its proposals from 0 again
+    let multisig_authority = Keypair::new();
+    svm.airdrop(&multisig_authority.pubkey(), 5 * LAMPORTS_PER_SOL)
+        .unwrap();
+    let members = vec![Keypair::new()];
+    svm.airdrop(&members[0].pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let (multisig_state_pda, multisig_vault_pda) =
+        create_multisig_native_vault(&mut svm, &program_id, &multisig_authority, &members, 1);
+    let proposal_pda = |generation: u64| {
+        Pubkey::find_program_address(
+            &[
+                b"proposal",
+                multisig_state_pda.as_ref(),
+                &generation.to_le_bytes(),
+                &0u64.to_le_bytes(),
+            ],
+            &program_id,
+        )
+        .0
+    };
+    let propose_ix = |generation: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(members[0].pubkey(), true),
+            AccountMeta::new(multisig_state_pda, false),
+            AccountMeta::new(proposal_pda(generation), false),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::CreateWithdrawalProposal {
+            amount: LAMPORTS_PER_SOL / 2,
+            destination: to_program_key(&members[0].pubkey()),
+        })
+        .unwrap(),
+    };
+    send(&mut svm, &[propose_ix(0)], &members[0], &[&members[0]]).expect("create proposal failed");
+
+    let mut close = vault_ix(
+        &program_id,
+        &VaultInstruction::Close,
+        &multisig_authority.pubkey(),
+        &multisig_state_pda,
+        &multisig_vault_pda,
+        &multisig_authority.pubkey(),
+    );
+    close
+        .accounts
+        .push(AccountMeta::new_readonly(members[0].pubkey(), true));
+    send(
+        &mut svm,
+        &[close],
+        &multisig_authority,
+        &[&multisig_authority, &members[0]],
+    )
+    .expect("close multisig vault failed");
+
+    create_multisig_native_vault(&mut svm, &program_id, &multisig_authority, &members, 1);
+    send(&mut svm, &[propose_ix(1)], &members[0], &[&members[0]])
+        .expect("create proposal 0 after reopen failed");
+    let proposal =
+        WithdrawalProposal::unpack(&svm.get_account(&proposal_pda(1)).unwrap().data).unwrap();
+    assert_eq!(proposal.proposal_id, 0);
+
+    let stale_vote = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(members[0].pubkey(), true),
+            AccountMeta::new_readonly(multisig_state_pda, false),
+            AccountMeta::new(proposal_pda(0), false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::RejectProposal).unwrap(),
+    };
+    let result = send(&mut svm, &[stale_vote], &members[0], &[&members[0]]);
+    assert_vault_error(result, VaultError::InvalidProposal);
+
+    // ...and a reopened timelocked vault its withdrawal requests
+    let timelocked_config = VaultConfig {
+        withdraw_delay: 3600,
+        ..VaultConfig::default()
+    };
+    let (timelocked_state_pda, _) = state_pda(&program_id, &authority.pubkey(), 1);
+    let (timelocked_vault_pda, _) = vault_pda(&program_id, &timelocked_state_pda);
+    let initialize_timelocked = initialize_ix_with_config(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &timelocked_vault_pda,
+        1,
+        true,
+        timelocked_config.clone(),
+    );
+    send(
+        &mut svm,
+        &[initialize_timelocked],
+        &authority,
+        &[&authority],
+    )
+    .expect("failed to create timelocked vault");
+
+    let pending_pda = |generation: u64| {
+        Pubkey::find_program_address(
+            &[
+                b"withdrawal",
+                timelocked_state_pda.as_ref(),
+                &generation.to_le_bytes(),
+                &0u64.to_le_bytes(),
+            ],
+            &program_id,
+        )
+        .0
+    };
+    let request_ix = |generation: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(timelocked_state_pda, false),
+            AccountMeta::new(pending_pda(generation), false),
+            AccountMeta::new(authority.pubkey(), true),
+            AccountMeta::new_readonly(program::ID, false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::RequestWithdraw {
+            amount: LAMPORTS_PER_SOL / 4,
+            destination: to_program_key(&authority.pubkey()),
+        })
+        .unwrap(),
+    };
+    send(&mut svm, &[request_ix(0)], &authority, &[&authority]).expect("request failed");
+
+    let close = vault_ix(
+        &program_id,
+        &VaultInstruction::Close,
+        &authority.pubkey(),
+        &timelocked_state_pda,
+        &timelocked_vault_pda,
+        &authority.pubkey(),
+    );
+    send(&mut svm, &[close], &authority, &[&authority]).expect("close timelocked vault failed");
+
+    let reopen_timelocked = initialize_ix_with_config(
+        &program_id,
+        &authority.pubkey(),
+        &program::ID,
+        &timelocked_vault_pda,
+        1,
+        true,
+        timelocked_config,
+    );
+    send(&mut svm, &[reopen_timelocked], &authority, &[&authority])
+        .expect("reopen timelocked vault failed");
+    send(&mut svm, &[request_ix(1)], &authority, &[&authority])
+        .expect("request 0 after reopen failed");
+
+    let stale_cancel = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new_readonly(timelocked_state_pda, false),
+            AccountMeta::new(pending_pda(0), false),
+        ],
+        data: borsh::to_vec(&VaultInstruction::CancelWithdraw).unwrap(),
+    };
+    let result = send(&mut svm, &[stale_cancel], &authority, &[&authority]);
+    assert_vault_error(result, VaultError::InvalidWithdrawalRequest);
+}